@@ -1,6 +1,5 @@
 extern "C" {
     fn malloc(s: usize) -> *mut u8;
-    fn calloc(s: usize, n:usize) -> *mut u8;
     fn free(ptr: *mut u8);
     fn realloc(ptr: *mut u8, s: usize)-> *mut u8;
 }
@@ -12,6 +11,7 @@ pub struct Vec<T> {
     elem_size: usize
 }
 impl<T: core::fmt::Debug> Vec<T> {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         let size = core::mem::size_of::<T>();
 
@@ -19,7 +19,7 @@ impl<T: core::fmt::Debug> Vec<T> {
         {
             malloc(size) as *mut T
         };
-        Self { len: 0, ptr: ptr, elem_size:size}
+        Self { len: 0, ptr, elem_size:size}
     }
     pub fn push(&mut self, value: T)
     {
@@ -53,6 +53,7 @@ impl<T: core::fmt::Debug> Vec<T> {
             None
         }
     }
+    #[allow(clippy::mut_from_ref)]
     pub fn get_mut(&self, idx: usize) -> Option<&mut T> {
         if idx < self.len {
             unsafe { Some(&mut *(self.ptr.add(idx))) }
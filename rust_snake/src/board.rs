@@ -0,0 +1,91 @@
+use crate::{pos::Pos, settings::Settings};
+
+/// Size of the playing field, from `settings.txt`:
+///
+/// ```text
+/// board_width=25
+/// board_height=14
+/// cell_size=64
+/// ```
+///
+/// `cell_size` only gives the default window size, the board is then scaled
+/// to fit whatever size the window has and letterboxed.
+/// Sizes are clamped to `4..=256` cells and `1..=256` pixels per cell.
+#[derive(Clone, Copy)]
+pub struct Board
+{
+    /// In cells
+    pub width: i16,
+    pub height: i16,
+    /// Pixels per cell in the default window
+    pub cell_size: i16,
+}
+
+//Assez de place pour tourner sans se mordre
+pub(crate) const MIN_SIZE: i32 = 4;
+//Au dela, le plateau ne se voit plus et les positions en i16 deborderaient
+pub(crate) const MAX_SIZE: i32 = 256;
+
+impl Board {
+    pub fn new(settings: &Settings) -> Board
+    {
+        Board
+        {
+            width: settings.number("board_width", 25).clamp(MIN_SIZE, MAX_SIZE) as _,
+            height: settings.number("board_height", 14).clamp(MIN_SIZE, MAX_SIZE) as _,
+            cell_size: settings.number("cell_size", 64).clamp(1, MAX_SIZE) as _,
+        }
+    }
+
+    /// Board of `width`x`height` cells, `None` outside of `4..=256` cells instead of clamping
+    pub fn sized(width: usize, height: usize, cell_size: i16) -> Option<Board>
+    {
+        let sizes = MIN_SIZE as usize..=MAX_SIZE as usize;
+        (sizes.contains(&width) && sizes.contains(&height))
+            .then_some(Board { width: width as i16, height: height as i16, cell_size })
+    }
+
+    /// Window size showing the board with `cell_size` pixels per cell
+    pub fn pixel_size(&self) -> (i32, i32)
+    {
+        (i32::from(self.width) * i32::from(self.cell_size), i32::from(self.height) * i32::from(self.cell_size))
+    }
+
+    pub fn center(&self) -> Pos
+    {
+        Pos { x: self.width / 2, y: self.height / 2 }
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool
+    {
+        pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height
+    }
+
+    /// Pixels per cell so that the whole board fits in `viewport`
+    pub fn fit_zoom(&self, viewport: (f32, f32)) -> f32
+    {
+        (viewport.0 / f32::from(self.width)).min(viewport.1 / f32::from(self.height))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn sizes_are_clamped()
+    {
+        let board = Board::new(&Settings::parse("board_width=70000\nboard_height=-3\ncell_size=0"));
+        assert_eq!((board.width, board.height, board.cell_size), (256, 4, 1));
+    }
+
+    #[test]
+    fn sized_rejects_sizes_out_of_bounds()
+    {
+        assert!(Board::sized(4, 256, 64).is_some());
+        assert!(Board::sized(3, 10, 64).is_none());
+        assert!(Board::sized(10, 257, 64).is_none());
+        assert!(Board::sized(40000, 10, 64).is_none());
+    }
+}
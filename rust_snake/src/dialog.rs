@@ -0,0 +1,39 @@
+#[cfg(target_os = "windows")]
+pub fn show_message(caption: &str, body: &str) {
+    use winapi::um::winuser::MB_ICONINFORMATION;
+    message_box(caption, body, MB_ICONINFORMATION);
+}
+
+#[cfg(target_os = "windows")]
+pub fn show_error(caption: &str, body: &str) {
+    use winapi::um::winuser::MB_ICONERROR;
+    message_box(caption, body, MB_ICONERROR);
+}
+
+#[cfg(target_os = "windows")]
+fn message_box(caption: &str, body: &str, icon: u32) {
+    use std::ffi::CString;
+    use winapi::um::winuser::{MessageBoxA, MB_OK};
+
+    let lp_text = CString::new(body).unwrap();
+    let lp_caption = CString::new(caption).unwrap();
+    unsafe {
+        MessageBoxA(
+            std::ptr::null_mut(),
+            lp_text.as_ptr(),
+            lp_caption.as_ptr(),
+            MB_OK | icon
+        );
+    }
+}
+
+//No message box outside of windows, the terminal will do
+#[cfg(not(target_os = "windows"))]
+pub fn show_message(caption: &str, body: &str) {
+    eprintln!("== {} ==\n{}", caption, body);
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn show_error(caption: &str, body: &str) {
+    eprintln!("== {} ==\n{}", caption, body);
+}
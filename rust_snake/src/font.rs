@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use winopengl::{AtlasBuilder, Sprite, SpriteBatch, TextureAtlas, Transform2D};
+
+use crate::sprites::SpriteId;
+
+//Police 5x7 minimale pour les outils de debug, majuscules seulement
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+//Un pixel d'espace entre deux lettres
+const ADVANCE: f32 = 6.;
+
+//Une ligne par u8, bit de poids fort a gauche
+const GLYPHS: [(char, [u8; GLYPH_HEIGHT]); 51] = [
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('<', [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010]),
+    ('>', [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+];
+
+//Pixels d'atlas par pixel de police: a l'echelle 2 des textes de debug, un texel par pixel
+//d'ecran, le filtrage lineaire de l'atlas ne floute pas les lettres
+const GLYPH_PIXEL: usize = 2;
+//Bloc plein, sa bordure repetee par l'atlas le garde uni
+const SOLID_SIZE: usize = 4;
+
+//Chaque lettre et le bloc plein dans l'atlas du jeu, a appeler avant AtlasBuilder::build
+pub fn add_to_atlas(builder: &mut AtlasBuilder<SpriteId>)
+{
+    let (width, height) = (GLYPH_WIDTH * GLYPH_PIXEL, GLYPH_HEIGHT * GLYPH_PIXEL);
+    for (c, rows) in GLYPHS
+    {
+        let mut bytes = vec![0u8; width * height * 4];
+        for y in 0..height
+        {
+            for x in 0..width
+            {
+                if rows[y / GLYPH_PIXEL] & (1 << (GLYPH_WIDTH - 1 - x / GLYPH_PIXEL)) != 0
+                {
+                    let i = (y * width + x) * 4;
+                    bytes[i..i + 4].copy_from_slice(&[255; 4]);
+                }
+            }
+        }
+        builder.add(SpriteId::Glyph(c), width as u32, height as u32, &bytes);
+    }
+    builder.add(SpriteId::Solid, SOLID_SIZE as u32, SOLID_SIZE as u32, &[255; SOLID_SIZE * SOLID_SIZE * 4]);
+}
+
+//Texte et rectangles pleins dessines avec le SpriteBatch, dans la texture de l'atlas
+pub struct Font
+{
+    glyphs: HashMap<char, Sprite>,
+    //Pixel blanc pour les rectangles
+    solid: Sprite,
+}
+
+impl Font {
+    pub fn new(atlas: &TextureAtlas<SpriteId>) -> Font
+    {
+        let glyphs = GLYPHS
+            .iter()
+            .map(|(c, _)| {
+                let sprite = atlas.sprite(&SpriteId::Glyph(*c));
+                //Taille en pixels de police, pour que scale reste l'echelle d'un pixel de police
+                (*c, Sprite { size: (GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32), ..sprite })
+            })
+            .collect();
+        //Le texel au centre du bloc plein, taille 1 pour etre mis a l'echelle directement
+        let block = atlas.sprite(&SpriteId::Solid);
+        let [u, v, w, h] = block.uv;
+        let solid = Sprite {
+            uv: [u + w / 2., v + h / 2., 0., 0.],
+            size: (1., 1.),
+            ..block
+        };
+        Font { glyphs, solid }
+    }
+
+    //Largeur d'un texte dans les unites de la projection
+    pub fn width(text: &str, scale: f32) -> f32
+    {
+        text.chars().count() as f32 * ADVANCE * scale
+    }
+
+    pub fn line_height(scale: f32) -> f32
+    {
+        (GLYPH_HEIGHT as f32 + 2.) * scale
+    }
+
+    //(x, y) est le coin en haut a gauche, y vers le haut, les caracteres inconnus sont sautes
+    pub fn text(&self, batch: &mut SpriteBatch, x: f32, y: f32, scale: f32, text: &str, tint: (u8, u8, u8, u8))
+    {
+        let center_y = y - GLYPH_HEIGHT as f32 * scale / 2.;
+        for (i, c) in text.chars().enumerate()
+        {
+            if let Some(glyph) = self.glyphs.get(&c.to_ascii_uppercase())
+            {
+                let center_x = x + (i as f32 * ADVANCE + GLYPH_WIDTH as f32 / 2.) * scale;
+                batch.add_sprite(*glyph, Transform2D::from_translation(center_x, center_y).scaled(scale, scale), tint);
+            }
+        }
+    }
+
+    //(x, y) est le coin en bas a gauche
+    pub fn rect(&self, batch: &mut SpriteBatch, x: f32, y: f32, width: f32, height: f32, tint: (u8, u8, u8, u8))
+    {
+        let transform = Transform2D::from_translation(x + width / 2., y + height / 2.).scaled(width, height);
+        batch.add_sprite(self.solid, transform, tint);
+    }
+}
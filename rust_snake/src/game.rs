@@ -1,22 +1,30 @@
-use std::{ffi::CString, time::Duration};
+use std::time::{Duration, SystemTime};
 
-use winopengl::{EventHandler, GraphicsContext, KeyCode};
-use rand::Rng;
-use winapi::um::winuser::{MessageBoxA, MB_OK, MB_ICONINFORMATION};
+use winopengl::{Camera2D, EventHandler, GraphicsContext, KeyCode, Owned, SpriteBatch, Texture, UniformDesc, UniformType};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 mod bonus;
 mod snake;
 mod background;
+mod overlay;
+mod console;
 use crate::pos::Pos;
+use crate::dialog;
+use crate::screenshot;
+use crate::settings::Settings;
+use crate::board::Board;
+use crate::post_process::PostProcess;
+use crate::sprites::build_atlas;
 
-use self::{snake::{Snake, Dir}, bonus::Bonus, background::Background};
+use self::{snake::{Snake, Dir}, bonus::Bonus, background::Background, overlay::Overlay, console::Console};
 
-use crate::images::bmp as Images;
-pub const BOX_SIZE: i16 = 64;
-pub const SCREEN_WIDTH: i16 = 1600;
-pub const SCREEN_HEIGHT: i16 = 896;
-pub const SCREEN_WIDTH_FLOAT: f32 = 1600.;
-pub const SCREEN_HEIGHT_FLOAT: f32 = 896.;
+const FLASH_DURATION: f32 = 0.4;
+//Zoom de la camera, 1 = tout le plateau
+const MIN_ZOOM: f32 = 1.;
+const MAX_ZOOM: f32 = 3.;
+const ZOOM_STEP: f32 = 1.25;
+//Vitesse a laquelle la camera rattrape la tete, par seconde
+const CAMERA_SPEED: f32 = 8.;
 
 #[derive(Clone, Copy)]
 #[derive(Debug)]
@@ -44,30 +52,77 @@ pub(crate) struct Game
     difficulty: Difficulty,
     bonus:Bonus,
     bg: Background,
+    //Une seule texture pour tous les sprites
+    batch: SpriteBatch,
+    //Le corps du serpent, avec le shader qui l'anime
+    body_batch: SpriteBatch,
+    _atlas: Owned<Texture>,
+    post: PostProcess,
+    overlay: Overlay,
+    console: Console,
+    camera: Camera2D,
+    zoom: f32,
+    fullscreen: bool,
+    flash_start: Option<SystemTime>,
     bonus_list: Vec<Pos>,
     score:i32,
     running:bool,
-    width:i16,
-    height:i16,
+    screenshot_requested:bool,
+    board: Board,
+    //Graine fixable depuis la console pour rejouer une partie
+    rng: StdRng,
+    //Pas de game over, le plateau boucle
+    god: bool,
 }
 impl Game
 {
-    pub(crate) fn new(ctx: &mut GraphicsContext) -> Game
+    pub(crate) fn new(ctx: &mut GraphicsContext, settings: &Settings, board: Board) -> Result<Game, winopengl::Error>
     {
+        //Pour savoir sur quoi tourne le jeu en cas de souci
+        eprintln!("{}", ctx.context_info());
+        //Une ligne par frame dans trace.csv pour le profilage
+        if settings.enabled("trace", false)
+        {
+            if !ctx.enable_gpu_timer(true)
+            {
+                eprintln!("Pas de timer GPU sur ce contexte");
+            }
+            if let Err(e) = ctx.start_trace("./trace.csv")
+            {
+                eprintln!("Trace impossible: {}", e);
+            }
+        }
+        let atlas = build_atlas(ctx);
         let mut g = Game
         {
-            snake: Snake::new(ctx),
+            snake: Snake::new(&atlas, board.center()),
             difficulty: get_difficulty(DifficultyLevel::Easy),
-            bonus: Bonus::new(ctx),
-            bg: Background::new(ctx),
+            bonus: Bonus::new(&atlas),
+            bg: Background::new(ctx, board, settings.enabled("background", true))?,
+            batch: SpriteBatch::new(ctx)?,
+            body_batch: SpriteBatch::with_fragment_shader(
+                ctx,
+                include_str!("./shaders/body_shader.fs"),
+                vec![UniformDesc::new("time", UniformType::Float1)],
+            )?,
+            _atlas: Owned::new(ctx, atlas.texture),
+            post: PostProcess::new(ctx, settings)?,
+            overlay: Overlay::new(ctx, &atlas, settings.enabled("debug", false))?,
+            console: Console::new(ctx, &atlas)?,
+            camera: new_camera(ctx, board),
+            zoom: MIN_ZOOM,
+            fullscreen: settings.enabled("fullscreen", false),
+            flash_start: None,
             bonus_list : Vec::new(),
-            width: SCREEN_WIDTH/BOX_SIZE,
-            height: SCREEN_HEIGHT/BOX_SIZE,
+            board,
             score: 0,
-            running: false
+            running: false,
+            screenshot_requested: false,
+            rng: StdRng::from_entropy(),
+            god: false
         };
         g.init();
-        g
+        Ok(g)
     }
 
     fn init(&mut self) {
@@ -77,14 +132,14 @@ impl Game
         self.difficulty = get_difficulty(DifficultyLevel::Easy);
         self.bonus_list = Vec::new();
         self.spawn_bonus();
+        self.camera.target = self.head_center();
     }
 
     fn spawn_bonus(&mut self){
         while self.bonus_list.len() < self.difficulty.bonus_count as _
         {
-            let mut rng = rand::thread_rng();
-            let x:i16 = rng.gen_range(0..self.width);
-            let y:i16 = rng.gen_range(0..self.height);
+            let x:i16 = self.rng.gen_range(0..self.board.width);
+            let y:i16 = self.rng.gen_range(0..self.board.height);
             self.bonus_list.push(Pos{ x, y });
         }
     }
@@ -120,14 +175,25 @@ impl Game
         }
     }
 
+    //Regle de sortie du plateau, aussi utilisee par l'overlay pour la prediction
+    fn hits_wall(&self, pos: Pos) -> bool {
+        !self.board.contains(pos)
+    }
+
     fn check_game_over(&mut self) {
-        if self.snake.pos.x < 0 || self.snake.pos.x > self.width 
-            || self.snake.pos.y < 0 || self.snake.pos.y > self.height 
+        if self.god
+        {
+            self.snake.pos.x = self.snake.pos.x.rem_euclid(self.board.width);
+            self.snake.pos.y = self.snake.pos.y.rem_euclid(self.board.height);
+            return;
+        }
+        if self.hits_wall(self.snake.pos)
             || self.snake.eat_himself()
         {
             self.running = false;            
             show_score(self.score);
             self.init();
+            self.flash_start = Some(SystemTime::now());
         }
     }
 
@@ -146,6 +212,34 @@ impl Game
         }
     }
 
+    fn flash_intensity(&mut self) -> f32 {
+        let elapsed = match self.flash_start {
+            Some(start) => start.elapsed().unwrap_or_default().as_secs_f32(),
+            None => return 0.,
+        };
+        if elapsed > FLASH_DURATION
+        {
+            self.flash_start = None;
+            return 0.;
+        }
+        1. - elapsed / FLASH_DURATION
+    }
+
+    fn head_center(&self) -> (f32, f32) {
+        (f32::from(self.snake.pos.x) + 0.5, f32::from(self.snake.pos.y) + 0.5)
+    }
+
+    //Suit la tete en restant dans le plateau
+    fn update_camera(&mut self, dt: f32) {
+        //Zoom 1: tout le plateau dans la fenetre, bandes noires autour
+        self.camera.zoom = self.board.fit_zoom(self.camera.viewport) * self.zoom;
+        let goal = self.head_center();
+        let t = (dt * CAMERA_SPEED).min(1.);
+        self.camera.target.0 += (goal.0 - self.camera.target.0) * t;
+        self.camera.target.1 += (goal.1 - self.camera.target.1) * t;
+        self.camera.clamp_to((f32::from(self.board.width), f32::from(self.board.height)));
+    }
+
     fn update_title(&self, ctx: &mut GraphicsContext) {        
         //WIN API MESSAGE SCORE
         let mut title = "AmbuSnake".to_owned();
@@ -192,32 +286,70 @@ fn get_difficulty(level: DifficultyLevel) -> Difficulty {
     }
 }
 
+fn new_camera(ctx: &GraphicsContext, board: Board) -> Camera2D {
+    let (width, height) = ctx.screen_size();
+    let viewport = (width as f32, height as f32);
+    Camera2D::new(viewport, board.fit_zoom(viewport))
+}
+
 fn show_score(score: i32) {
     //WIN API MESSAGE SCORE
     let mut message_body = "Vous avez perdu\n\n".to_owned();
     message_body += "Score: ";
     message_body += &score.to_string();
 
-    let lp_text = CString::new(message_body).unwrap();
-    let lp_caption = CString::new("GAME OVER").unwrap();
-    unsafe {
-        MessageBoxA(
-            std::ptr::null_mut(),
-            lp_text.as_ptr(),
-            lp_caption.as_ptr(),
-            MB_OK | MB_ICONINFORMATION
-        );
-    }
+    dialog::show_message("GAME OVER", &message_body);
 }
 
 impl EventHandler for Game 
 {
     fn key_down_event(&mut self, ctx: &mut GraphicsContext, _keycode: KeyCode) 
     {
+        if _keycode == KeyCode::GraveAccent || (self.console.open && _keycode == KeyCode::Escape)
+        {
+            self.console.open = !self.console.open;
+            //Le serpent attend pendant qu'on tape
+            self.running = false;
+            return;
+        }
+        if self.console.open
+        {
+            if let Some(line) = self.console.key_down(_keycode)
+            {
+                match self.run_command(ctx, &line) {
+                    Ok(message) if message.is_empty() => (),
+                    Ok(message) => self.console.print(&message),
+                    Err(error) => self.console.print(&error),
+                }
+            }
+            return;
+        }
         if _keycode == KeyCode::Escape
         { 
             ctx.order_quit()
         }
+        if _keycode == KeyCode::F12
+        {
+            //Taken at the end of the next draw
+            self.screenshot_requested = true;
+        }
+        if _keycode == KeyCode::F11
+        {
+            self.fullscreen = !self.fullscreen;
+            ctx.set_fullscreen(self.fullscreen);
+        }
+        if _keycode == KeyCode::PageUp
+        {
+            self.zoom = (self.zoom * ZOOM_STEP).min(MAX_ZOOM);
+        }
+        if _keycode == KeyCode::PageDown
+        {
+            self.zoom = (self.zoom / ZOOM_STEP).max(MIN_ZOOM);
+        }
+        if _keycode == KeyCode::F3
+        {
+            self.overlay.enabled = !self.overlay.enabled;
+        }
         //On attend un premier input pour pas lancer tout de suite le jeu
         if !self.running
         {
@@ -242,8 +374,29 @@ impl EventHandler for Game
         }
     }
 
-    fn update(&mut self, ctx: &mut GraphicsContext) 
+    fn resize_event(&mut self, ctx: &mut GraphicsContext, width: i32, height: i32)
+    {
+        //Le zoom pour voir tout le plateau est recalcule a chaque frame
+        self.camera.viewport = (width as f32, height as f32);
+        self.post.resize(ctx, width, height);
+    }
+
+    fn char_event(&mut self, _ctx: &mut GraphicsContext, character: char)
+    {
+        if self.console.open
+        {
+            self.console.char(character);
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut GraphicsContext, x: f32, y: f32)
+    {
+        self.overlay.mouse = Some((x, y));
+    }
+
+    fn update(&mut self, ctx: &mut GraphicsContext, dt: f32) 
     { 
+        self.update_camera(dt);
         if self.running
         {
             self.real_game_update(ctx);
@@ -252,19 +405,161 @@ impl EventHandler for Game
 
     fn draw(&mut self, ctx: &mut GraphicsContext) 
     {
-        ctx.begin_default_pass();
+        //En cases, origine en bas a gauche du plateau
+        let projection = self.camera.projection();
+        self.post.begin_scene(ctx);
 
-        self.bg.draw(ctx);
+        self.bg.draw(ctx, &projection);
 
         //SnakeDraw
-        self.snake.draw(ctx);
+        self.snake.draw(&mut self.batch, &mut self.body_batch);
         for b in &self.bonus_list
         {
-            self.bonus.draw(ctx, *b);
+            self.bonus.draw(&mut self.batch, *b);
         }
+        //La tete par dessus le corps
+        self.body_batch.draw(ctx, &projection);
+        self.batch.draw(ctx, &projection);
 
         ctx.end_render_pass();
 
+        //Bonuses again, alone, for the bloom
+        if self.post.begin_glow(ctx)
+        {
+            for b in &self.bonus_list
+            {
+                self.bonus.draw(&mut self.batch, *b);
+            }
+            self.batch.draw(ctx, &projection);
+            ctx.end_render_pass();
+        }
+
+        let grayscale = if self.running { 0. } else { 1. };
+        self.post.set_param("grayscale", "grayscale", (grayscale, 0.));
+        let flash = self.flash_intensity();
+        self.post.set_param("flash", "flash", (flash, 0.));
+        self.post.apply(ctx);
+        //Apres le post-process pour rester lisible
+        self.draw_overlay(ctx);
+        self.console.draw(ctx, self.camera.viewport);
+
+        if self.screenshot_requested
+        {
+            self.screenshot_requested = false;
+            match screenshot::save(ctx) {
+                Ok(file_name) => eprintln!("Screenshot saved to {}", file_name),
+                Err(e) => eprintln!("Screenshot failed: {}", e),
+            }
+        }
+
         ctx.commit_frame();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests
+{
+    use std::{cell::RefCell, rc::Rc};
+
+    use winopengl::{conf::Conf, EventHandler, GraphicsContext};
+
+    use super::Game;
+    use crate::{board::Board, screenshot, settings::Settings};
+
+    const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/golden/start.bmp");
+
+    //Dessine une frame du jeu et garde ses pixels
+    struct Capture
+    {
+        game: Game,
+        frame: Rc<RefCell<Option<Vec<u8>>>>,
+    }
+
+    impl EventHandler for Capture
+    {
+        fn update(&mut self, ctx: &mut GraphicsContext, dt: f32)
+        {
+            self.game.update(ctx, dt);
+        }
+
+        fn draw(&mut self, ctx: &mut GraphicsContext)
+        {
+            self.game.draw(ctx);
+            *self.frame.borrow_mut() = Some(ctx.read_pixels());
+        }
+    }
+
+    //Lance une partie neuve avec le handler rendu par f.
+    //Sans contexte headless le test echoue, sauf si WINOPENGL_SKIP_GL_TESTS est defini: false
+    fn run_game<H: EventHandler + 'static>(conf: Conf, f: impl FnOnce(Game, &mut GraphicsContext) -> H + 'static) -> bool
+    {
+        let result = winopengl::start(conf, move |ctx| {
+            //Reglages par defaut quel que soit le settings.txt du dossier, fond gris meme si la carte existe
+            let settings = Settings::parse("background=off");
+            let game = Game::new(ctx, &settings, Board::new(&settings))?;
+            Ok(Box::new(f(game, ctx)))
+        });
+        match result
+        {
+            Err(winopengl::Error::Context(e)) if std::env::var_os("WINOPENGL_SKIP_GL_TESTS").is_some() =>
+            {
+                eprintln!("Pas de contexte headless, test ignore: {}", e);
+                false
+            }
+            Err(e) => panic!("{} (WINOPENGL_SKIP_GL_TESTS pour ignorer les tests GL)", e),
+            Ok(()) => true,
+        }
+    }
+
+    //Lance f sur une partie neuve, sans frame
+    pub(super) fn with_game(f: impl FnOnce(&mut Game, &mut GraphicsContext) + 'static) -> bool
+    {
+        let conf = Conf
+        {
+            headless_frames: Some(0),
+            ..Default::default()
+        };
+        run_game(conf, |mut game, ctx| {
+            f(&mut game, ctx);
+            game
+        })
+    }
+
+    //UPDATE_GOLDEN=1 cargo test pour regenerer l'image de reference
+    #[test]
+    fn first_frame_matches_golden_image()
+    {
+        let (width, height) = (160, 120);
+        let frame = Rc::new(RefCell::new(None));
+        let captured = frame.clone();
+        let conf = Conf
+        {
+            window_width: width,
+            window_height: height,
+            headless_frames: Some(1),
+            ..Default::default()
+        };
+        let started = run_game(conf, move |mut game, ctx| {
+            game.run_command(ctx, "seed 1").unwrap();
+            Capture { game, frame: captured }
+        });
+        if !started
+        {
+            return;
+        }
+        let pixels = frame.borrow_mut().take().expect("no frame drawn");
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some()
+        {
+            screenshot::write_bmp(GOLDEN, width as u32, height as u32, &pixels).unwrap();
+        }
+        let (golden_width, golden_height, golden) = screenshot::read_bmp(GOLDEN).unwrap();
+        assert_eq!((golden_width, golden_height), (width as u32, height as u32));
+        //Marge pour les arrondis d'un autre driver
+        if let Err(e) = screenshot::compare(&golden, &pixels, 8, 64)
+        {
+            let actual = concat!(env!("CARGO_MANIFEST_DIR"), "/golden/start_actual.bmp");
+            let _ = screenshot::write_bmp(actual, width as u32, height as u32, &pixels);
+            panic!("{}, frame written to {}", e, actual);
+        }
+    }
+}
@@ -1,25 +1,28 @@
 use std::{fs::File, io::{BufReader, Read}};
 
-use winopengl::GraphicsContext;
-use crate::{graphical_object::{GraphicalObject}, pos::Pos, texture::{TextureData, get_texture}};
+use winopengl::{GraphicsContext, Mat4, ShaderError, Transform2D};
+use crate::{graphical_object::{GraphicalObject}, texture::{TextureData, get_texture}, board::Board};
 pub(crate) struct Background
 {
-    graphic: GraphicalObject
+    graphic: GraphicalObject,
+    //Image etiree sur tout le plateau
+    transform: Transform2D,
+    //false: toujours le gris, meme si la carte existe
+    image: bool,
 }
 impl Background {    
-    pub(crate) fn new(ctx: &mut GraphicsContext) -> Background 
+    pub(crate) fn new(ctx: &mut GraphicsContext, board: Board, image: bool) -> Result<Background, ShaderError> 
     { 
-        //BG NOIR SI PAS D'IMAGE SIMPLE VECTEUR u8 de Size 1600x896x4
-        let open_file = File::open("./Map/Strasbourg.bmp");
+        //BG GRIS SI PAS D'IMAGE, UN SEUL PIXEL ETIRE
+        let open_file = if image { File::open("./Map/Strasbourg.bmp").ok() } else { None };
         
-        let buffer = vec![70u8; 1600*896*3];
         let mut texture = TextureData
         {
-            width: 1600,
-            height: 896,
-            data: buffer
+            width: 1,
+            height: 1,
+            data: vec![70u8, 70, 70, 255]
         };
-        if let Ok(file) = open_file
+        if let Some(file) = open_file
         {
             let mut file_buffer = Vec::new();
             let mut reader = BufReader::new(file);
@@ -27,14 +30,28 @@ impl Background {
             texture = get_texture(&file_buffer as &[u8]);
         }
 
-        Background 
+        Ok(Background 
         {
-            graphic: GraphicalObject::new(ctx, texture, false) 
-        }
+            transform: get_transform(&texture, board),
+            graphic: GraphicalObject::new(ctx, texture)?,
+            image,
+        })
+    }
+
+    //Meme fond pour un autre plateau
+    pub(crate) fn with_board(&self, ctx: &mut GraphicsContext, board: Board) -> Result<Background, ShaderError>
+    {
+        Background::new(ctx, board, self.image)
     }
     
-    pub fn draw(&mut self, ctx: &mut GraphicsContext)
+    pub fn draw(&mut self, ctx: &mut GraphicsContext, projection: &Mat4)
     {
-        self.graphic.draw(ctx, Pos{ x: 0, y: 0});        
+        self.graphic.draw(ctx, projection, self.transform);        
     }
-}
\ No newline at end of file
+}
+
+fn get_transform(texture: &TextureData, board: Board) -> Transform2D {
+    let (width, height) = (f32::from(board.width), f32::from(board.height));
+    Transform2D::from_translation(width / 2., height / 2.)
+        .scaled(width / f32::from(texture.width), height / f32::from(texture.height))
+}
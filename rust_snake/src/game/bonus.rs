@@ -1,24 +1,22 @@
-use winopengl::GraphicsContext;
-use crate::game::Images::{SNAKE_BONUS};
+use winopengl::{Sprite, SpriteBatch, TextureAtlas};
 use crate::pos::Pos;
-use crate::graphical_object::GraphicalObject;
-use crate::texture::get_texture;
+use crate::sprites::{SpriteId, WHITE, get_transform};
 
 pub(crate) struct Bonus
 {
-    graphic: GraphicalObject
+    sprite: Sprite
 }
 impl Bonus {    
-    pub(crate) fn new(ctx: &mut GraphicsContext) -> Bonus 
+    pub(crate) fn new(atlas: &TextureAtlas<SpriteId>) -> Bonus 
     {
         Bonus 
         {
-            graphic: GraphicalObject::new(ctx, get_texture(SNAKE_BONUS), false) 
+            sprite: atlas.sprite(&SpriteId::Bonus)
         }
     }
     
-    pub fn draw(&mut self, ctx :&mut GraphicsContext, pos: Pos) 
+    pub fn draw(&self, batch: &mut SpriteBatch, pos: Pos) 
     {
-        self.graphic.draw(ctx, pos);        
+        batch.add_sprite(self.sprite, get_transform(pos, self.sprite), WHITE);
     }
 }
\ No newline at end of file
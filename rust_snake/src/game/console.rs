@@ -0,0 +1,372 @@
+use std::{collections::VecDeque, fs};
+
+use rand::{rngs::StdRng, SeedableRng};
+use winopengl::{ortho, GraphicsContext, KeyCode, PassAction, ShaderError, SpriteBatch, TextureAtlas};
+
+use crate::{board::{Board, MAX_SIZE, MIN_SIZE}, font::Font, pos::Pos, sprites::SpriteId};
+
+use super::{get_difficulty, DifficultyLevel, Game};
+
+const TEXT_SCALE: f32 = 2.;
+const MARGIN: f32 = 8.;
+//Lignes gardees dans le journal, et celles affichees
+const LOG_SIZE: usize = 200;
+const VISIBLE_LINES: usize = 12;
+
+const PANEL: (u8, u8, u8, u8) = (0, 0, 0, 235);
+const TEXT: (u8, u8, u8, u8) = (255, 255, 255, 255);
+const PROMPT: (u8, u8, u8, u8) = (255, 220, 0, 255);
+
+//Pour la completion avec Tab, les arguments libres ne sont pas completes
+const COMMANDS: [&str; 14] = [
+    "spawn bonus",
+    "grow",
+    "set difficulty easy",
+    "set difficulty medium",
+    "set difficulty hard",
+    "set difficulty insane",
+    "seed",
+    "god on",
+    "god off",
+    "load level",
+    "screenshot",
+    "clear",
+    "help",
+    "exit",
+];
+
+const HELP: [&str; 9] = [
+    "spawn bonus X Y",
+    "grow N",
+    "set difficulty easy|medium|hard|insane",
+    "seed N       restart with a fixed bonus sequence",
+    "god on|off   no game over, walls wrap",
+    "load level NAME   from levels/NAME.txt",
+    "screenshot",
+    "clear",
+    "exit",
+];
+
+//Console de dev qui descend du haut de l'ecran, touche sous Echap pour l'ouvrir
+pub(super) struct Console
+{
+    pub open: bool,
+    input: String,
+    history: Vec<String>,
+    //Entree de l'historique en cours d'edition, None pour une nouvelle ligne
+    browsing: Option<usize>,
+    log: VecDeque<String>,
+    batch: SpriteBatch,
+    font: Font,
+}
+
+impl Console {
+    pub(super) fn new(ctx: &mut GraphicsContext, atlas: &TextureAtlas<SpriteId>) -> Result<Console, ShaderError>
+    {
+        Ok(Console
+        {
+            open: false,
+            input: String::new(),
+            history: Vec::new(),
+            browsing: None,
+            log: VecDeque::with_capacity(LOG_SIZE),
+            batch: SpriteBatch::new(ctx)?,
+            font: Font::new(atlas),
+        })
+    }
+
+    pub(super) fn print(&mut self, line: &str)
+    {
+        if self.log.len() == LOG_SIZE
+        {
+            self.log.pop_front();
+        }
+        self.log.push_back(line.to_owned());
+    }
+
+    //La ligne validee avec Entree, deja ajoutee a l'historique
+    pub(super) fn key_down(&mut self, keycode: KeyCode) -> Option<String>
+    {
+        match keycode {
+            KeyCode::Enter => {
+                let line = std::mem::take(&mut self.input).trim().to_owned();
+                self.browsing = None;
+                if line.is_empty()
+                {
+                    return None;
+                }
+                self.print(&format!("> {}", line));
+                if self.history.last() != Some(&line)
+                {
+                    self.history.push(line.clone());
+                }
+                return Some(line);
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Tab => self.complete(),
+            KeyCode::Up => {
+                let i = match self.browsing {
+                    Some(i) => i.saturating_sub(1),
+                    None if !self.history.is_empty() => self.history.len() - 1,
+                    None => return None,
+                };
+                self.browsing = Some(i);
+                self.input = self.history[i].clone();
+            }
+            KeyCode::Down => {
+                if let Some(i) = self.browsing
+                {
+                    self.browsing = if i + 1 < self.history.len() { Some(i + 1) } else { None };
+                    self.input = self.browsing.map(|i| self.history[i].clone()).unwrap_or_default();
+                }
+            }
+            _ => (),
+        }
+        None
+    }
+
+    pub(super) fn char(&mut self, character: char)
+    {
+        //Le caractere de la touche qui ouvre la console
+        if character != '`' && character != '²'
+        {
+            self.input.push(character);
+        }
+    }
+
+    //Complete jusqu'au plus long prefixe commun, liste les choix s'il y en a plusieurs
+    fn complete(&mut self)
+    {
+        let input = self.input.trim_start().to_lowercase();
+        let matches: Vec<&str> = COMMANDS.iter().copied().filter(|c| c.starts_with(&input)).collect();
+        let Some(first) = matches.first() else {
+            return;
+        };
+        let common = matches.iter().fold(first.len(), |len, m| {
+            first.bytes().zip(m.bytes()).take(len).take_while(|(a, b)| a == b).count()
+        });
+        if matches.len() == 1
+        {
+            self.input = format!("{} ", first);
+        }
+        else if common > input.len()
+        {
+            self.input = first[..common].to_owned();
+        }
+        else
+        {
+            let choices = matches.join(", ");
+            self.print(&choices);
+        }
+    }
+
+    pub(super) fn draw(&mut self, ctx: &mut GraphicsContext, viewport: (f32, f32))
+    {
+        if !self.open
+        {
+            return;
+        }
+        let (width, height) = viewport;
+        let line_height = Font::line_height(TEXT_SCALE);
+        let panel_h = (VISIBLE_LINES + 1) as f32 * line_height + 2. * MARGIN;
+
+        self.font.rect(&mut self.batch, 0., height - panel_h, width, panel_h, PANEL);
+        let mut y = height - MARGIN;
+        for line in self.log.iter().skip(self.log.len().saturating_sub(VISIBLE_LINES))
+        {
+            self.font.text(&mut self.batch, MARGIN, y, TEXT_SCALE, line, TEXT);
+            y -= line_height;
+        }
+        let prompt = format!("> {}_", self.input);
+        self.font.text(&mut self.batch, MARGIN, height - panel_h + MARGIN + line_height, TEXT_SCALE, &prompt, PROMPT);
+
+        ctx.begin_pass(None, PassAction::Nothing);
+        self.batch.draw(ctx, &ortho(0., width, 0., height));
+        ctx.end_render_pass();
+    }
+}
+
+impl Game {
+    //Le texte a afficher en retour, ou l'erreur
+    pub(super) fn run_command(&mut self, ctx: &mut GraphicsContext, line: &str) -> Result<String, String>
+    {
+        let words: Vec<String> = line.split_whitespace().map(|w| w.to_lowercase()).collect();
+        let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+        match words.as_slice() {
+            ["spawn", "bonus", x, y] => {
+                let pos = Pos { x: parse(x)?, y: parse(y)? };
+                if !self.board.contains(pos)
+                {
+                    return Err(format!("({},{}) is off the board", pos.x, pos.y));
+                }
+                self.bonus_list.push(pos);
+                Ok(format!("bonus at ({},{})", pos.x, pos.y))
+            }
+            ["grow", count] => {
+                let count: u16 = parse(count)?;
+                for _ in 0..count
+                {
+                    self.snake.grow();
+                }
+                Ok(format!("body is {} long", self.snake.body().len()))
+            }
+            ["set", "difficulty", level] => {
+                let level = match *level {
+                    "easy" => DifficultyLevel::Easy,
+                    "medium" => DifficultyLevel::Medium,
+                    "hard" => DifficultyLevel::Hard,
+                    "insane" => DifficultyLevel::Insane,
+                    _ => return Err(format!("unknown difficulty {}", level)),
+                };
+                self.difficulty = get_difficulty(level);
+                self.spawn_bonus();
+                Ok(format!("difficulty {:?}", level))
+            }
+            ["seed", seed] => {
+                let seed: u64 = parse(seed)?;
+                self.rng = StdRng::seed_from_u64(seed);
+                self.init();
+                Ok(format!("restarted with seed {}", seed))
+            }
+            ["god", "on"] => {
+                self.god = true;
+                Ok("god mode on".to_owned())
+            }
+            ["god", "off"] => {
+                self.god = false;
+                Ok("god mode off".to_owned())
+            }
+            ["load", "level", name] => self.load_level(ctx, name),
+            ["screenshot"] => {
+                self.screenshot_requested = true;
+                Ok("screenshot requested".to_owned())
+            }
+            ["clear"] => {
+                self.console.log.clear();
+                Ok(String::new())
+            }
+            ["help"] => {
+                for line in HELP
+                {
+                    self.console.print(line);
+                }
+                Ok(String::new())
+            }
+            ["exit"] => {
+                self.console.open = false;
+                Ok(String::new())
+            }
+            _ => Err(format!("unknown command: {}, try help", line)),
+        }
+    }
+
+    //Une ligne de texte par rangee, du haut vers le bas:
+    //'o' un bonus, 'S' le depart du serpent, le reste vide
+    fn load_level(&mut self, ctx: &mut GraphicsContext, name: &str) -> Result<String, String>
+    {
+        if name.contains(['/', '\\', '.'])
+        {
+            return Err(format!("bad level name {}", name));
+        }
+        let path = format!("./levels/{}.txt", name);
+        let content = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+        let rows: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+        let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+        let board = Board::sized(width, rows.len(), self.board.cell_size).ok_or_else(|| {
+            format!(
+                "{}: the board is {}x{}, it must be between {min}x{min} and {max}x{max}",
+                path,
+                width,
+                rows.len(),
+                min = MIN_SIZE,
+                max = MAX_SIZE
+            )
+        })?;
+
+        let mut start = board.center();
+        let mut bonus = Vec::new();
+        for (row, line) in rows.iter().enumerate()
+        {
+            //Premiere ligne du fichier en haut du plateau
+            let y = board.height - 1 - row as i16;
+            for (x, c) in line.chars().enumerate()
+            {
+                let pos = Pos { x: x as i16, y };
+                match c {
+                    'o' => bonus.push(pos),
+                    'S' => start = pos,
+                    _ => (),
+                }
+            }
+        }
+
+        self.bg = self.bg.with_board(ctx, board).map_err(|e| e.to_string())?;
+        self.board = board;
+        self.snake.set_start(start);
+        self.init();
+        if !bonus.is_empty()
+        {
+            self.bonus_list = bonus;
+        }
+        Ok(format!("level {} {}x{}", name, board.width, board.height))
+    }
+}
+
+fn parse<T: std::str::FromStr>(word: &str) -> Result<T, String>
+{
+    word.parse().map_err(|_| format!("{} is not a valid number", word))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use winopengl::KeyCode;
+
+    use crate::game::tests::with_game;
+
+    #[test]
+    fn parses_commands_and_their_arguments()
+    {
+        with_game(|game, ctx| {
+            assert_eq!(game.run_command(ctx, "  GROW   3 "), Ok("body is 3 long".to_owned()));
+            assert_eq!(game.run_command(ctx, "spawn bonus 1 2"), Ok("bonus at (1,2)".to_owned()));
+            assert!(game.bonus_list.iter().any(|b| b.x == 1 && b.y == 2));
+            assert!(game.run_command(ctx, "spawn bonus -1 2").is_err());
+            assert_eq!(game.run_command(ctx, "grow many"), Err("many is not a valid number".to_owned()));
+            assert!(game.run_command(ctx, "set difficulty extreme").is_err());
+            assert!(game.run_command(ctx, "grow").is_err());
+            assert!(game.run_command(ctx, "load level ../secret").is_err());
+            assert_eq!(game.run_command(ctx, "god on"), Ok("god mode on".to_owned()));
+            assert!(game.god);
+        });
+    }
+
+    #[test]
+    fn completes_and_recalls_lines()
+    {
+        with_game(|game, _| {
+            let console = &mut game.console;
+            for c in "set d".chars()
+            {
+                console.char(c);
+            }
+            console.key_down(KeyCode::Tab);
+            assert_eq!(console.input, "set difficulty ");
+            console.key_down(KeyCode::Tab);
+            assert_eq!(console.log.back().map(|l| l.as_str()), Some("set difficulty easy, set difficulty medium, set difficulty hard, set difficulty insane"));
+
+            console.input = "grow 2".to_owned();
+            assert_eq!(console.key_down(KeyCode::Enter), Some("grow 2".to_owned()));
+            console.input = "god on".to_owned();
+            console.key_down(KeyCode::Enter);
+            console.key_down(KeyCode::Up);
+            console.key_down(KeyCode::Up);
+            assert_eq!(console.input, "grow 2");
+            console.key_down(KeyCode::Down);
+            console.key_down(KeyCode::Down);
+            assert_eq!(console.input, "");
+        });
+    }
+}
@@ -0,0 +1,193 @@
+use winopengl::{ortho, GraphicsContext, PassAction, ShaderError, SpriteBatch, TextureAtlas};
+
+use crate::{font::Font, pos::Pos, sprites::SpriteId};
+
+use super::Game;
+
+//Taille du texte en pixels de police
+const TEXT_SCALE: f32 = 2.;
+//Epaisseur des lignes en pixels ecran
+const LINE_WIDTH: f32 = 1.;
+const MARGIN: f32 = 8.;
+
+const GRID: (u8, u8, u8, u8) = (255, 255, 255, 40);
+const BORDER: (u8, u8, u8, u8) = (255, 255, 255, 160);
+const BONUS: (u8, u8, u8, u8) = (255, 220, 0, 220);
+const MOUSE: (u8, u8, u8, u8) = (255, 255, 255, 60);
+const SAFE: (u8, u8, u8, u8) = (0, 255, 0, 90);
+const DEATH: (u8, u8, u8, u8) = (255, 0, 0, 120);
+//Hors du plateau mais pas game over, en mode god
+const OUTSIDE: (u8, u8, u8, u8) = (255, 128, 0, 160);
+const TEXT: (u8, u8, u8, u8) = (255, 255, 255, 255);
+const PANEL: (u8, u8, u8, u8) = (0, 0, 0, 160);
+
+//Infos de debug par dessus le jeu, F3 pour afficher
+pub(super) struct Overlay
+{
+    pub enabled: bool,
+    //Position de la souris en pixels fenetre
+    pub mouse: Option<(f32, f32)>,
+    batch: SpriteBatch,
+    font: Font,
+}
+
+impl Overlay {
+    pub(super) fn new(ctx: &mut GraphicsContext, atlas: &TextureAtlas<SpriteId>, enabled: bool) -> Result<Overlay, ShaderError>
+    {
+        Ok(Overlay
+        {
+            enabled,
+            mouse: None,
+            batch: SpriteBatch::new(ctx)?,
+            font: Font::new(atlas),
+        })
+    }
+}
+
+impl Game {
+    fn mouse_cell(&self) -> Option<Pos>
+    {
+        let (x, y) = self.camera.screen_to_world(self.overlay.mouse?);
+        let cell = Pos { x: x.floor() as i16, y: y.floor() as i16 };
+        if self.board.contains(cell) { Some(cell) } else { None }
+    }
+
+    //Texte en haut a gauche, une entree par ligne
+    fn overlay_lines(&self, ctx: &GraphicsContext) -> Vec<String>
+    {
+        let stats = ctx.stats();
+        let median = stats.frame_time_percentile(50.);
+        let fps = if median > 0. { 1. / median } else { 0. };
+        let mut lines = vec![format!(
+            "FPS {:.0}  FRAME {:.1}MS  P99 {:.1}MS",
+            fps,
+            median * 1000.,
+            stats.frame_time_percentile(99.) * 1000.
+        )];
+
+        let tick = self.difficulty.move_duration.as_millis();
+        lines.push(if self.running
+        {
+            format!("TICK {}/{}MS", self.snake.move_elapsed().as_millis().min(tick), tick)
+        }
+        else
+        {
+            format!("TICK -/{}MS PAUSE", tick)
+        });
+
+        let head = self.snake.pos;
+        lines.push(format!(
+            "HEAD ({},{})  DIR {:?}  NEXT {:?}  BODY {}",
+            head.x,
+            head.y,
+            self.snake.dir,
+            self.snake.next_dir(),
+            self.snake.body().len()
+        ));
+
+        let next = self.snake.next_pos();
+        let wall = self.hits_wall(next);
+        let body = self.snake.will_eat_himself();
+        lines.push(format!(
+            "NEXT ({},{})  {}  WALL {}  BODY {}",
+            next.x,
+            next.y,
+            if self.board.contains(next) { "IN" } else { "OUT" },
+            if wall { "YES" } else { "NO" },
+            if body { "YES" } else { "NO" }
+        ));
+
+        let bonus: Vec<String> = self.bonus_list.iter().map(|b| format!("({},{})", b.x, b.y)).collect();
+        lines.push(format!("BONUS {}", bonus.join(" ")));
+
+        lines.push(match self.mouse_cell() {
+            Some(cell) => format!("MOUSE ({},{})", cell.x, cell.y),
+            None => "MOUSE -".to_owned(),
+        });
+        lines.push(format!("BOARD {}X{}", self.board.width, self.board.height));
+        lines
+    }
+
+    pub(super) fn draw_overlay(&mut self, ctx: &mut GraphicsContext)
+    {
+        if !self.overlay.enabled
+        {
+            return;
+        }
+        let lines = self.overlay_lines(ctx);
+        let mouse_cell = self.mouse_cell();
+        let next = self.snake.next_pos();
+        //En mode god rien ne tue, le serpent passe de l'autre cote
+        let next_color = if !self.god && (self.hits_wall(next) || self.snake.will_eat_himself())
+        {
+            DEATH
+        }
+        else if !self.board.contains(next)
+        {
+            OUTSIDE
+        }
+        else
+        {
+            SAFE
+        };
+
+        let Overlay { batch, font, .. } = &mut self.overlay;
+        ctx.begin_pass(None, PassAction::Nothing);
+
+        //Dans le monde, en cases
+        let line = LINE_WIDTH / self.camera.zoom;
+        let (width, height) = (f32::from(self.board.width), f32::from(self.board.height));
+        for x in 0..=self.board.width
+        {
+            let color = if x == 0 || x == self.board.width { BORDER } else { GRID };
+            font.rect(batch, f32::from(x) - line / 2., 0., line, height, color);
+        }
+        for y in 0..=self.board.height
+        {
+            let color = if y == 0 || y == self.board.height { BORDER } else { GRID };
+            font.rect(batch, 0., f32::from(y) - line / 2., width, line, color);
+        }
+        if let Some(cell) = mouse_cell
+        {
+            font.rect(batch, f32::from(cell.x), f32::from(cell.y), 1., 1., MOUSE);
+        }
+        for b in &self.bonus_list
+        {
+            outline(font, batch, *b, 3. * line, BONUS);
+        }
+        font.rect(batch, f32::from(next.x), f32::from(next.y), 1., 1., next_color);
+        batch.draw(ctx, &self.camera.projection());
+
+        //A l'ecran, en pixels, y vers le haut
+        let (screen_w, screen_h) = self.camera.viewport;
+        let line_height = Font::line_height(TEXT_SCALE);
+        for (i, b) in self.snake.body().iter().enumerate()
+        {
+            let label = i.to_string();
+            let (x, y) = self.camera.world_to_screen((f32::from(b.x) + 0.5, f32::from(b.y) + 0.5));
+            let x = x - Font::width(&label, TEXT_SCALE) / 2.;
+            let y = screen_h - y + line_height / 2.;
+            font.text(batch, x, y, TEXT_SCALE, &label, TEXT);
+        }
+
+        let panel_w = lines.iter().map(|l| Font::width(l, TEXT_SCALE)).fold(0., f32::max) + 2. * MARGIN;
+        let panel_h = lines.len() as f32 * line_height + MARGIN;
+        font.rect(batch, 0., screen_h - panel_h, panel_w, panel_h, PANEL);
+        for (i, text) in lines.iter().enumerate()
+        {
+            font.text(batch, MARGIN, screen_h - MARGIN - i as f32 * line_height, TEXT_SCALE, text, TEXT);
+        }
+        batch.draw(ctx, &ortho(0., screen_w, 0., screen_h));
+
+        ctx.end_render_pass();
+    }
+}
+
+fn outline(font: &Font, batch: &mut SpriteBatch, pos: Pos, line: f32, color: (u8, u8, u8, u8))
+{
+    let (x, y) = (f32::from(pos.x), f32::from(pos.y));
+    font.rect(batch, x, y, 1., line, color);
+    font.rect(batch, x, y + 1. - line, 1., line, color);
+    font.rect(batch, x, y + line, line, 1. - 2. * line, color);
+    font.rect(batch, x + 1. - line, y + line, line, 1. - 2. * line, color);
+}
@@ -1,12 +1,18 @@
 use std::time::{SystemTime, Duration};
 
-use winopengl::{GraphicsContext};
+use std::f32::consts::{FRAC_PI_2, PI};
 
-use crate::{game::Images::{SNAKE_HEAD, SNAKE_BODY}, pos::Pos, graphical_object::{GraphicalObject, ROTATION}, texture::{get_texture}};
+use winopengl::{Sprite, SpriteBatch, TextureAtlas};
 
-use super::{SCREEN_WIDTH, SCREEN_HEIGHT, BOX_SIZE};
+use crate::{pos::Pos, sprites::{SpriteId, WHITE, get_transform}};
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+
+//Animations de la tete, en secondes
+const TURN_DURATION: f32 = 0.1;
+const POP_DURATION: f32 = 0.25;
+const POP_SCALE: f32 = 0.3;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Dir 
 {
     Left,
@@ -18,31 +24,45 @@ pub enum Dir
 pub struct Snake
 {   
     //ForDrawing
-    head: GraphicalObject,
-    body: GraphicalObject,
+    head: Sprite,
+    body: Sprite,
     //ForLogic    
     pub pos: Pos,
     //DIRECTION
     pub dir: Dir,
     next_dir: Dir,
+    //Angle de la tete avant le dernier virage
+    turn_from: f32,
+    turn_start: SystemTime,
+    //Depuis le dernier bonus mange
+    pop_start: Option<SystemTime>,
+    //Case de depart
+    start: Pos,
     //WEIRD STUFF
     body_part: Vec<Pos>,
     last_move_start: SystemTime,
+    //Horloge du clignotement du corps
+    created: SystemTime,
 }
 impl Snake {
-    pub(crate) fn new(ctx: &mut GraphicsContext) -> Snake 
+    pub(crate) fn new(atlas: &TextureAtlas<SpriteId>, start: Pos) -> Snake 
     {
         Snake
         {
             body_part: Vec::new(),   
             dir: Dir::Right,        
             next_dir: Dir::Right,         
-            pos:Pos { x: SCREEN_WIDTH/(2*BOX_SIZE), y: SCREEN_HEIGHT/(2*BOX_SIZE) },    
+            turn_from: get_rotation(Dir::Right),
+            turn_start: SystemTime::now(),
+            pop_start: None,
+            pos: start,
+            start,
             last_move_start:SystemTime::now(),
+            created: SystemTime::now(),
             
             //ForDrawing
-            body: GraphicalObject::new(ctx, get_texture(SNAKE_BODY), false),
-            head: GraphicalObject::new(ctx, get_texture(SNAKE_HEAD), false)
+            body: atlas.sprite(&SpriteId::Body),
+            head: atlas.sprite(&SpriteId::Head)
         }
     }
 
@@ -50,11 +70,17 @@ impl Snake {
         self.body_part = Vec::new();
         self.dir = Dir::Right;
         self.next_dir = Dir::Right;
-        self.pos.x = SCREEN_WIDTH/(2*BOX_SIZE);
-        self.pos.y = SCREEN_HEIGHT/(2*BOX_SIZE);
+        self.turn_from = get_rotation(Dir::Right);
+        self.pop_start = None;
+        self.pos = self.start;
     }    
 
 
+    //Prise en compte au prochain reset
+    pub(crate) fn set_start(&mut self, start: Pos) {
+        self.start = start;
+    }
+
     pub fn start(&mut self) {
         self. last_move_start = SystemTime::now();
     }
@@ -113,47 +139,54 @@ impl Snake {
     
     pub fn grow(&mut self) 
     {
-        if self.body_part.is_empty()
-        {
-            self.body.start_shader_time();
-        }
         self.body_part.push(Pos{ x:-1, y: -1});
+        self.pop_start = Some(SystemTime::now());
     }
     
-    pub fn draw(&mut self, ctx: &mut GraphicsContext) {
-        self.head.rotate(ctx, get_rotation(self.dir));
-        self.head.draw(ctx, self.pos);
+    //Le corps dans son propre batch, son shader anime les ecailles
+    pub fn draw(&self, batch: &mut SpriteBatch, body_batch: &mut SpriteBatch) {
+        //Virage progressif vers la nouvelle direction
+        let turn = (seconds_since(self.turn_start) / TURN_DURATION).min(1.);
+        let target = get_rotation(self.dir);
+        //Par le plus court chemin
+        let mut delta = (target - self.turn_from) % (2. * PI);
+        if delta > PI
+        {
+            delta -= 2. * PI;
+        }
+        else if delta < -PI
+        {
+            delta += 2. * PI;
+        }
+        let rotation = self.turn_from + delta * turn;
+
+        //Grossit un instant quand on mange un bonus
+        let scale = match self.pop_start {
+            Some(start) => 1. + POP_SCALE * (1. - seconds_since(start) / POP_DURATION).max(0.),
+            None => 1.,
+        };
+
+        batch.add_sprite(self.head, get_transform(self.pos, self.head).rotated(rotation).scaled(scale, scale), WHITE);
         //SnakeDraw
+        body_batch.set_uniforms(&[seconds_since(self.created).fract()]);
         for b in &self.body_part
         {
-            self.body.draw(ctx, *b);
+            body_batch.add_sprite(self.body, get_transform(*b, self.body), WHITE);
         }
     }
 
     fn compute_target(&mut self) {
+        if self.next_dir != self.dir
+        {
+            self.turn_from = get_rotation(self.dir);
+            self.turn_start = SystemTime::now();
+        }
         self.dir = self.next_dir;
 
         //BODY TARGET
         let mut last_x = self.pos.x;
         let mut last_y = self.pos.y;
-        match self.dir {
-            Dir::Left => 
-            {   
-                self.pos.x -=  1;
-            },
-            Dir::Right => 
-            {
-                self.pos.x += 1;      
-            },
-            Dir::Up => 
-            {
-                self.pos.y += 1;           
-            },
-            Dir::Down => 
-            {
-                self.pos.y -= 1;        
-            }
-        }
+        self.pos = step(self.pos, self.dir);
         for b in self.body_part.iter_mut()
         {
             let curr_x = b.x;
@@ -165,6 +198,31 @@ impl Snake {
         }
     }
 
+    //Pour l'overlay de debug
+    pub(crate) fn next_dir(&self) -> Dir {
+        self.next_dir
+    }
+
+    pub(crate) fn body(&self) -> &[Pos] {
+        &self.body_part
+    }
+
+    pub(crate) fn move_elapsed(&self) -> Duration {
+        self.last_move_start.elapsed().unwrap_or_default()
+    }
+
+    //Case de la tete au prochain pas
+    pub(crate) fn next_pos(&self) -> Pos {
+        step(self.pos, self.next_dir)
+    }
+
+    //Le corps aura avance d'une case, la queue libere la sienne
+    pub(crate) fn will_eat_himself(&self) -> bool {
+        let next = self.next_pos();
+        let len = self.body_part.len().saturating_sub(1);
+        self.body_part[..len].iter().any(|b| b.x == next.x && b.y == next.y)
+    }
+
     pub(crate) fn eat_himself(&self) -> bool {        
         for b in &self.body_part
         {
@@ -178,11 +236,25 @@ impl Snake {
     }    
 }
 
-fn get_rotation(dir: Dir) -> ROTATION {
+fn step(pos: Pos, dir: Dir) -> Pos {
+    match dir {
+        Dir::Left => Pos { x: pos.x - 1, y: pos.y },
+        Dir::Right => Pos { x: pos.x + 1, y: pos.y },
+        Dir::Up => Pos { x: pos.x, y: pos.y + 1 },
+        Dir::Down => Pos { x: pos.x, y: pos.y - 1 },
+    }
+}
+
+fn seconds_since(start: SystemTime) -> f32 {
+    start.elapsed().map_or(0., |t| t.as_secs_f32())
+}
+
+//Le sprite de la tete regarde vers le haut, angle en radians sens trigo
+fn get_rotation(dir: Dir) -> f32 {
     match dir {
-        Dir::Left => ROTATION::Clockwise270,
-        Dir::Right => ROTATION::Clockwise90,
-        Dir::Up => ROTATION::None,
-        Dir::Down => ROTATION::Clockwise180,
+        Dir::Left => FRAC_PI_2,
+        Dir::Right => -FRAC_PI_2,
+        Dir::Up => 0.,
+        Dir::Down => PI,
     }
 }
\ No newline at end of file
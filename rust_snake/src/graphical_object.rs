@@ -1,85 +1,60 @@
-use std::time::SystemTime;
-
 use winopengl::{
-    Bindings, Buffer, BufferType, GraphicsContext, Pipeline, Shader, Texture,
-    VertexAttribute, ShaderMeta, UniformBlockLayout, UniformDesc,
+    AnyResource, Bindings, BlendState, BufferLayout, Buffer, BufferType, GraphicsContext, Mat4, Owned, Pipeline, PipelineParams, Resource, Shader, ShaderError,
+    Transform2D, VertexAttribute, mat4_mul, VertexFormat, ShaderMeta, UniformBlockLayout, UniformDesc, UniformType,
 };
 
-use crate::{pos::Pos, texture::TextureData, game::{SCREEN_WIDTH_FLOAT, SCREEN_HEIGHT_FLOAT}};
+use crate::texture::TextureData;
 
-pub enum ROTATION
-{
-    None,
-    Clockwise90,
-    Clockwise180,
-    Clockwise270
-}
 pub struct GraphicalObject {
     //ForDrawing
-    width: f32,
-    height: f32,
-    x_offset: f32,
-    y_offset: f32,
     bindings: Bindings,
     pipeline: Pipeline,
-    time: SystemTime
+    //Supprimes du GPU avec l'objet
+    _resources: Vec<Owned<AnyResource>>,
 }
 impl GraphicalObject {
-    pub fn start_shader_time(&mut self)
-    {
-        self.time = SystemTime::now();
-    }
-
-    pub fn rotate(&mut self, ctx: &mut GraphicsContext, rotation: ROTATION)
-    {
-        let square_vertices: [Vertex; 4] = get_rot_vertex(rotation, self.width, self.height);    
-        let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &square_vertices);
-        self.bindings.vertex_buffers = vec![vertex_buffer];
-    }
-
-    pub fn draw(&mut self, ctx: &mut GraphicsContext, pos:Pos) {
+    //transform place le centre du sprite, en pixels
+    pub fn draw(&mut self, ctx: &mut GraphicsContext, projection: &Mat4, transform: Transform2D) {
         ctx.apply_pipeline(&self.pipeline);
         ctx.apply_bindings(&self.bindings);
         ctx.apply_uniforms(&Uniforms {
-            offset: (
-                self.x_offset + (2. * f32::from(pos.x) / (SCREEN_WIDTH_FLOAT/self.width)) - 1.,
-                self.y_offset + (2. * f32::from(pos.y) / (SCREEN_HEIGHT_FLOAT/self.height)) - 1.,
-            )
+            transform: mat4_mul(projection, &transform.matrix()),
         });
         ctx.draw(0, 6, 1);
     }
 
-    pub(crate) fn new(ctx: &mut GraphicsContext, texture: TextureData, body: bool) -> GraphicalObject 
+    pub(crate) fn new(ctx: &mut GraphicsContext, texture: TextureData) -> Result<GraphicalObject, ShaderError> 
     { 
-        let widhtf = f32::from(texture.width);
-        let heightf = f32::from(texture.height);
-
-        let x_offset = widhtf / SCREEN_WIDTH_FLOAT;
-        let y_offset = heightf / SCREEN_HEIGHT_FLOAT;
-        let now = SystemTime::now();
-
-        GraphicalObject {
-            width: widhtf,
-            height: heightf,
-            x_offset,
-            y_offset,
-            bindings: init_bindings(ctx, texture),
-            pipeline: init_pipeline(ctx, body),
-            time: now
-        }
+        //Le shader d'abord, rien a liberer s'il echoue
+        let (pipeline, shader) = init_pipeline(ctx)?;
+        let bindings = init_bindings(ctx, texture);
+
+        let resources = [
+            bindings.vertex_buffers[0].into_any(),
+            bindings.index_buffer.into_any(),
+            bindings.images[0].into_any(),
+            pipeline.into_any(),
+            shader.into_any(),
+        ].iter().map(|r| Owned::new(ctx, *r)).collect();
+
+        Ok(GraphicalObject {
+            bindings,
+            pipeline,
+            _resources: resources,
+        })
     }       
 }
 
 fn init_bindings(ctx: &mut GraphicsContext, texture: TextureData, ) -> Bindings {
     let widthf = f32::from(texture.width);
     let heightf = f32::from(texture.height);
-    let square_vertices: [Vertex; 4] = get_rot_vertex(ROTATION::None, widthf, heightf);
+    let square_vertices: [Vertex; 4] = get_vertex(widthf, heightf);
     let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &square_vertices);
 
     let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
     let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
 
-    let texture = Texture::new(ctx, &texture.data as &[u8], texture.width as _, texture.height as _);
+    let texture = texture.upload(ctx);
     
     Bindings {
         vertex_buffers: vec![vertex_buffer],
@@ -88,72 +63,43 @@ fn init_bindings(ctx: &mut GraphicsContext, texture: TextureData, ) -> Bindings
     }
 }
 
-fn init_pipeline(ctx: &mut GraphicsContext, body: bool) -> Pipeline {    
+fn init_pipeline(ctx: &mut GraphicsContext) -> Result<(Pipeline, Shader), ShaderError> {    
     let vertex_shader:&str = std::str::from_utf8(include_bytes!("./shaders/shader.vs")).unwrap();
-    let mut fragment_shader = std::str::from_utf8(include_bytes!("./shaders/shader.fs")).unwrap();
-    if body
-    {
-        fragment_shader = std::str::from_utf8(include_bytes!("./shaders/body_shader.fs")).unwrap();
-    }
+    let fragment_shader = std::str::from_utf8(include_bytes!("./shaders/shader.fs")).unwrap();
         
-    let shader = Shader::new(ctx, vertex_shader, fragment_shader, meta()).unwrap();
+    let shader = Shader::new(ctx, vertex_shader, fragment_shader, meta())?;
 
-    Pipeline::new(
+    let pipeline = Pipeline::with_params(
         ctx,
+        &[BufferLayout::default()],
         &[
-            VertexAttribute::new("pos"),
-            VertexAttribute::new("uv"),
+            VertexAttribute::new("pos", VertexFormat::Float2),
+            VertexAttribute::new("uv", VertexFormat::Float2),
         ],
-        shader)
+        shader,
+        PipelineParams {
+            color_blend: Some(BlendState::alpha()),
+            ..Default::default()
+        });
+    Ok((pipeline, shader))
 }
 
-fn get_rot_vertex(rotation: ROTATION, width: f32, height: f32) -> [Vertex; 4] {
-    match rotation {
-        ROTATION::None =>
-        {
-            [
-                Vertex { pos : Vec2 { x: -width/SCREEN_WIDTH_FLOAT, y: -height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 0., y: 1. } },
-                Vertex { pos : Vec2 { x:  width/SCREEN_WIDTH_FLOAT, y: -height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 1., y: 1. } },
-                Vertex { pos : Vec2 { x:  width/SCREEN_WIDTH_FLOAT, y:  height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 1., y: 0. } },
-                Vertex { pos : Vec2 { x: -width/SCREEN_WIDTH_FLOAT, y:  height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 0., y: 0. } },
-            ]
-        },
-        ROTATION::Clockwise90 =>
-        {
-            [
-                Vertex { pos : Vec2 { x: -width/SCREEN_WIDTH_FLOAT, y: -height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 0., y: 1. } },
-                Vertex { pos : Vec2 { x:  width/SCREEN_WIDTH_FLOAT, y: -height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 0., y: 0. } },
-                Vertex { pos : Vec2 { x:  width/SCREEN_WIDTH_FLOAT, y:  height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 1., y: 0. } },
-                Vertex { pos : Vec2 { x: -width/SCREEN_WIDTH_FLOAT, y:  height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 1., y: 1. } },
-            ]
-        },
-        ROTATION::Clockwise180 =>
-        {
-            [
-                Vertex { pos: Vec2 { x: -width / SCREEN_WIDTH_FLOAT, y: -height / SCREEN_HEIGHT_FLOAT, }, uv: Vec2 { x: 0., y: 0. }, },
-                Vertex { pos: Vec2 { x: width / SCREEN_WIDTH_FLOAT, y: -height / SCREEN_HEIGHT_FLOAT, }, uv: Vec2 { x: 1., y: 0. }, },
-                Vertex { pos: Vec2 { x: width / SCREEN_WIDTH_FLOAT, y: height / SCREEN_HEIGHT_FLOAT, }, uv: Vec2 { x: 1., y: 1. }, },
-                Vertex { pos: Vec2 { x: -width / SCREEN_WIDTH_FLOAT, y: height / SCREEN_HEIGHT_FLOAT, }, uv: Vec2 { x: 0., y: 1. }, },
-            ]
-        },
-        ROTATION::Clockwise270 =>
-        {
-            [
-                Vertex { pos : Vec2 { x: -width/SCREEN_WIDTH_FLOAT, y: -height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 1., y: 0. } },
-                Vertex { pos : Vec2 { x:  width/SCREEN_WIDTH_FLOAT, y: -height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 1., y: 1. } },
-                Vertex { pos : Vec2 { x:  width/SCREEN_WIDTH_FLOAT, y:  height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 0., y: 1. } },
-                Vertex { pos : Vec2 { x: -width/SCREEN_WIDTH_FLOAT, y:  height/SCREEN_HEIGHT_FLOAT }, uv: Vec2 { x: 0., y: 0. } },
-            ]
-        }
-    }
+//Quad en pixels centre sur 0
+fn get_vertex(width: f32, height: f32) -> [Vertex; 4] {
+    let (x, y) = (width / 2., height / 2.);
+    [
+        Vertex { pos : Vec2 { x: -x, y: -y }, uv: Vec2 { x: 0., y: 1. } },
+        Vertex { pos : Vec2 { x, y: -y }, uv: Vec2 { x: 1., y: 1. } },
+        Vertex { pos : Vec2 { x, y }, uv: Vec2 { x: 1., y: 0. } },
+        Vertex { pos : Vec2 { x: -x, y }, uv: Vec2 { x: 0., y: 0. } },
+    ]
 }
 
-
-
 #[repr(C)]
 pub struct Uniforms 
 {
-    pub offset: (f32, f32)
+    //Projection * transform du sprite
+    pub transform: Mat4,
 }
 
 #[repr(C)]
@@ -171,7 +117,9 @@ pub fn meta() -> ShaderMeta {
     ShaderMeta {
         images: vec!["tex".to_string()],
         uniforms: UniformBlockLayout {
-            uniforms: vec![UniformDesc::new("offset")],
+            uniforms: vec![
+                UniformDesc::new("transform", UniformType::Mat4),
+            ],
         },
     }
 }
\ No newline at end of file
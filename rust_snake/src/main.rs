@@ -1,38 +1,64 @@
-use std::ffi::CString;
-
 //#![no_std]
-use game::{SCREEN_HEIGHT, SCREEN_WIDTH};
-use winapi::um::winuser::{MessageBoxA, MB_ICONINFORMATION, MB_OK};
-use winopengl::conf::Conf;
+use board::Board;
+use settings::Settings;
+use winopengl::conf::{Conf, LoopMode};
+mod board;
+mod dialog;
 mod graphical_object;
 mod texture;
 mod pos;
 mod images;
 mod game;
+mod screenshot;
+mod settings;
+mod post_process;
+mod sprites;
+mod font;
+
+//Taille max de texture des drivers courants, la fenetre et ses passes n'iront pas au dela
+const MAX_WINDOW_SIZE: i32 = 16384;
 
 fn main() {
     let mut message_body = "Vous avez trouver le snake cacher\n".to_owned();
     message_body += "Utilisez les fleches pour tourner\n";
     message_body += "Utilisez P pour mettre le jeu en pause\n";
+    message_body += "Utilisez F11 pour passer en plein ecran\n";
+    message_body += "Utilisez F12 pour faire une capture d'ecran\n";
+    message_body += "Utilisez PageUp et PageDown pour zoomer\n";
+    message_body += "Utilisez F3 pour les infos de debug\n";
+    message_body += "Utilisez la touche sous Echap pour la console\n";
     message_body += "Utilisez Echap pour quitter\n";
 
-    let lp_text = CString::new(message_body).unwrap();
-    let lp_caption = CString::new("Felicitation").unwrap();
-    unsafe {
-        MessageBoxA(
-            std::ptr::null_mut(),
-            lp_text.as_ptr(),
-            lp_caption.as_ptr(),
-            MB_OK | MB_ICONINFORMATION
-        );
-    }
+    dialog::show_message("Felicitation", &message_body);
 
-    winopengl::start(
+    //Taille de fenetre libre, le plateau est mis a l'echelle
+    let settings = Settings::load();
+    let board = Board::new(&settings);
+    let (width, height) = board.pixel_size();
+    let result = winopengl::start(
         Conf {
             window_title: "AmbuSnake".to_string(),
-            window_width: SCREEN_WIDTH as _,
-            window_height: SCREEN_HEIGHT as _
+            window_width: settings.number("window_width", width).clamp(1, MAX_WINDOW_SIZE),
+            window_height: settings.number("window_height", height).clamp(1, MAX_WINDOW_SIZE),
+            window_resizable: true,
+            fullscreen: settings.enabled("fullscreen", false),
+            high_dpi: settings.enabled("high_dpi", false),
+            //Erreurs GL sur stderr, lent
+            gl_debug: settings.enabled("gl_debug", false),
+            swap_interval: if settings.enabled("vsync", true) { 1 } else { 0 },
+            //Le serpent bouge au plus 10 fois par seconde, inutile de prendre tout un coeur
+            loop_mode: match settings.number("fps", 60) {
+                fps if fps > 0 => LoopMode::TargetFps(fps as u32),
+                _ => LoopMode::Continuous,
+            },
+            ..Default::default()
         },
-        |ctx| Box::new(game::Game::new(ctx)),
+        move |ctx| Ok(Box::new(game::Game::new(ctx, &settings, board)?)),
     );
+    //Pas de fenetre ou pas d'OpenGL: on explique au lieu de crasher
+    if let Err(e) = result
+    {
+        dialog::show_error("Impossible de lancer le jeu", &e.to_string());
+        std::process::exit(1);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,325 @@
+use winopengl::{
+    AnyResource, Bindings, BufferLayout, Buffer, BufferType, FramebufferError, GraphicsContext, Owned, PassAction, Pipeline,
+    RenderPass, Resource, Shader, ShaderError, ShaderMeta, Texture, TextureFormat, TextureParams,
+    UniformBlockLayout, UniformDesc, UniformType, VertexAttribute, VertexFormat,
+};
+
+use crate::{graphical_object::{Vec2, Vertex}, settings::Settings};
+
+/// One step of the chain: a fragment shader reading the previous step output as `tex`
+pub struct EffectDesc
+{
+    pub name: &'static str,
+    pub fragment_shader: &'static str,
+    /// vec2 uniforms of the shader with their starting value
+    pub params: &'static [(&'static str, (f32, f32))],
+    /// sample the glow pass as `glow`
+    pub uses_glow: bool,
+}
+
+/// Available effects, applied in this order unless the `post` setting gives another one
+pub const EFFECTS: &[EffectDesc] = &[
+    EffectDesc {
+        name: "bloom",
+        fragment_shader: include_str!("./shaders/post_bloom.fs"),
+        params: &[("texel", (0., 0.)), ("bloom", (1.5, 2.))],
+        uses_glow: true,
+    },
+    EffectDesc {
+        name: "flash",
+        fragment_shader: include_str!("./shaders/post_flash.fs"),
+        params: &[("flash", (0., 0.))],
+        uses_glow: false,
+    },
+    EffectDesc {
+        name: "grayscale",
+        fragment_shader: include_str!("./shaders/post_grayscale.fs"),
+        params: &[("grayscale", (0., 0.))],
+        uses_glow: false,
+    },
+    EffectDesc {
+        name: "crt",
+        fragment_shader: include_str!("./shaders/post_crt.fs"),
+        params: &[("scanlines", (448., 0.25))],
+        uses_glow: false,
+    },
+    EffectDesc {
+        name: "vignette",
+        fragment_shader: include_str!("./shaders/post_vignette.fs"),
+        params: &[("vignette", (0.55, 0.4))],
+        uses_glow: false,
+    },
+];
+
+struct Effect
+{
+    desc: &'static EffectDesc,
+    enabled: bool,
+    pipeline: Pipeline,
+    params: Vec<f32>,
+}
+
+/// Game::draw goes into `scene`, then every enabled effect is applied one after the other,
+/// ping-ponging between two targets, the last one drawing to the screen.
+pub struct PostProcess
+{
+    scene: RenderPass,
+    glow: RenderPass,
+    targets: [RenderPass; 2],
+    effects: Vec<Effect>,
+    copy: Pipeline,
+    bindings: Bindings,
+    //Tout ce qui est cree ici, supprime avec le PostProcess
+    _resources: Vec<Owned<AnyResource>>,
+    //Les cibles de rendu a part, recreees quand la fenetre change de taille
+    _targets: Vec<Owned<AnyResource>>,
+}
+
+impl PostProcess {
+    pub fn new(ctx: &mut GraphicsContext, settings: &Settings) -> Result<PostProcess, winopengl::Error>
+    {
+        let (width, height) = ctx.screen_size();
+        let vertex_shader = include_str!("./shaders/post.vs");
+        let mut resources = Vec::new();
+
+        //post=bloom,crt,vignette choisit les effets et leur ordre
+        let chain: Vec<&'static EffectDesc> = match settings.list("post") {
+            Some(names) => names.iter().filter_map(|name| {
+                let desc = EFFECTS.iter().find(|desc| desc.name == *name);
+                if desc.is_none()
+                {
+                    eprintln!("Effet {} inconnu", name);
+                }
+                desc
+            }).collect(),
+            None => EFFECTS.iter().collect(),
+        };
+
+        let effects = chain.into_iter().map(|desc| {
+            let mut params = Vec::new();
+            for (param, (x, y)) in desc.params
+            {
+                //crt.scanlines=448,0.25, ou juste le premier nombre
+                let values = settings.numbers(&format!("{}.{}", desc.name, param)).unwrap_or_default();
+                params.push(values.first().copied().unwrap_or(*x));
+                params.push(values.get(1).copied().unwrap_or(*y));
+            }
+            Ok(Effect {
+                desc,
+                enabled: settings.enabled(desc.name, true),
+                pipeline: init_pipeline(ctx, vertex_shader, desc, &mut resources)?,
+                params,
+            })
+        }).collect::<Result<_, ShaderError>>()?;
+
+        let copy = init_pipeline(ctx, vertex_shader, &EffectDesc {
+            name: "copy",
+            fragment_shader: include_str!("./shaders/post_copy.fs"),
+            params: &[],
+            uses_glow: false,
+        }, &mut resources)?;
+
+        let mut target_resources = Vec::new();
+        let (scene, glow, targets) = new_targets(ctx, width, height, &mut target_resources)?;
+        let bindings = init_bindings(ctx);
+        resources.push(bindings.vertex_buffers[0].into_any());
+        resources.push(bindings.index_buffer.into_any());
+
+        let mut post = PostProcess {
+            scene,
+            glow,
+            targets,
+            effects,
+            copy,
+            bindings,
+            _resources: resources.into_iter().map(|r| Owned::new(ctx, r)).collect(),
+            _targets: target_resources.into_iter().map(|r| Owned::new(ctx, r)).collect(),
+        };
+        post.set_param("bloom", "texel", (1. / width as f32, 1. / height as f32));
+        Ok(post)
+    }
+
+    /// Recreate the render targets at the new window size
+    pub fn resize(&mut self, ctx: &mut GraphicsContext, width: i32, height: i32)
+    {
+        let mut target_resources = Vec::new();
+        let created = new_targets(ctx, width, height, &mut target_resources);
+        //Liberees avec les anciennes cibles, ou tout de suite si la creation a echoue
+        let owned = target_resources.into_iter().map(|r| Owned::new(ctx, r)).collect();
+        match created {
+            Ok((scene, glow, targets)) => {
+                self.scene = scene;
+                self.glow = glow;
+                self.targets = targets;
+                self._targets = owned;
+                self.set_param("bloom", "texel", (1. / width as f32, 1. / height as f32));
+            }
+            //On garde les anciennes, l'image sera juste etiree
+            Err(e) => eprintln!("Cibles de rendu {}x{} impossibles: {}", width, height, e),
+        }
+    }
+
+    pub fn set_param(&mut self, effect: &str, param: &str, value: (f32, f32))
+    {
+        for e in self.effects.iter_mut().filter(|e| e.desc.name == effect)
+        {
+            if let Some(i) = e.desc.params.iter().position(|(name, _)| *name == param)
+            {
+                e.params[2 * i] = value.0;
+                e.params[2 * i + 1] = value.1;
+            }
+        }
+    }
+
+    fn is_enabled(&self, effect: &str) -> bool
+    {
+        self.effects.iter().any(|e| e.enabled && e.desc.name == effect)
+    }
+
+    /// Start drawing the frame, to be closed with `end_render_pass`
+    pub fn begin_scene(&mut self, ctx: &mut GraphicsContext)
+    {
+        ctx.begin_pass(Some(self.scene), PassAction::default());
+    }
+
+    /// Start drawing what should glow, returns false when bloom is off and nothing should be drawn
+    pub fn begin_glow(&mut self, ctx: &mut GraphicsContext) -> bool
+    {
+        if !self.is_enabled("bloom")
+        {
+            return false;
+        }
+        ctx.begin_pass(Some(self.glow), PassAction::default());
+        true
+    }
+
+    /// Run the chain, the result ends in the default framebuffer
+    pub fn apply(&mut self, ctx: &mut GraphicsContext)
+    {
+        let glow = self.glow.texture(ctx);
+        let mut input = self.scene.texture(ctx);
+        let enabled: Vec<&Effect> = self.effects.iter().filter(|e| e.enabled).collect();
+
+        for (i, effect) in enabled.iter().enumerate()
+        {
+            let last = i + 1 == enabled.len();
+            let target = if last { None } else { Some(self.targets[i % 2]) };
+            ctx.begin_pass(target, PassAction::Nothing);
+            self.bindings.images = if effect.desc.uses_glow { vec![input, glow] } else { vec![input] };
+            ctx.apply_pipeline(&effect.pipeline);
+            ctx.apply_bindings(&self.bindings);
+            //Tout le Vec de floats, une paire par parametre du shader
+            unsafe {
+                ctx.apply_uniforms_from_bytes(
+                    effect.params.as_ptr() as *const u8,
+                    effect.params.len() * std::mem::size_of::<f32>(),
+                );
+            }
+            ctx.draw(0, 6, 1);
+            ctx.end_render_pass();
+            if let Some(target) = target
+            {
+                input = target.texture(ctx);
+            }
+        }
+
+        if enabled.is_empty()
+        {
+            ctx.begin_pass(None, PassAction::Nothing);
+            self.bindings.images = vec![input];
+            ctx.apply_pipeline(&self.copy);
+            ctx.apply_bindings(&self.bindings);
+            ctx.draw(0, 6, 1);
+            ctx.end_render_pass();
+        }
+    }
+}
+
+//scene, glow et les deux cibles du ping-pong
+fn new_targets(ctx: &mut GraphicsContext, width: i32, height: i32, resources: &mut Vec<AnyResource>)
+    -> Result<(RenderPass, RenderPass, [RenderPass; 2]), FramebufferError>
+{
+    Ok((
+        new_target(ctx, width, height, true, resources)?,
+        new_target(ctx, width, height, false, resources)?,
+        [
+            new_target(ctx, width, height, false, resources)?,
+            new_target(ctx, width, height, false, resources)?,
+        ],
+    ))
+}
+
+fn new_target(ctx: &mut GraphicsContext, width: i32, height: i32, depth: bool, resources: &mut Vec<AnyResource>) -> Result<RenderPass, FramebufferError>
+{
+    let params = TextureParams {
+        width: width as _,
+        height: height as _,
+        ..Default::default()
+    };
+    let color = Texture::new_render_texture(ctx, params);
+    let depth = if depth
+    {
+        Some(Texture::new_render_texture(ctx, TextureParams { format: TextureFormat::Depth, ..params }))
+    }
+    else
+    {
+        None
+    };
+    resources.push(color.into_any());
+    if let Some(depth) = depth
+    {
+        resources.push(depth.into_any());
+    }
+    let pass = RenderPass::new(ctx, color, depth)?;
+    resources.push(pass.into_any());
+    Ok(pass)
+}
+
+fn init_bindings(ctx: &mut GraphicsContext) -> Bindings
+{
+    //Render target textures start bottom left like clip space, so uv just follows pos
+    let quad: [Vertex; 4] = [
+        Vertex { pos: Vec2 { x: -1., y: -1. }, uv: Vec2 { x: 0., y: 0. } },
+        Vertex { pos: Vec2 { x:  1., y: -1. }, uv: Vec2 { x: 1., y: 0. } },
+        Vertex { pos: Vec2 { x:  1., y:  1. }, uv: Vec2 { x: 1., y: 1. } },
+        Vertex { pos: Vec2 { x: -1., y:  1. }, uv: Vec2 { x: 0., y: 1. } },
+    ];
+    let vertex_buffer = Buffer::immutable(ctx, BufferType::VertexBuffer, &quad);
+
+    let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+    let index_buffer = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+
+    Bindings {
+        vertex_buffers: vec![vertex_buffer],
+        index_buffer,
+        images: vec![],
+    }
+}
+
+fn init_pipeline(ctx: &mut GraphicsContext, vertex_shader: &str, desc: &EffectDesc, resources: &mut Vec<AnyResource>) -> Result<Pipeline, ShaderError>
+{
+    let mut images = vec!["tex".to_string()];
+    if desc.uses_glow
+    {
+        images.push("glow".to_string());
+    }
+    let meta = ShaderMeta {
+        images,
+        uniforms: UniformBlockLayout {
+            uniforms: desc.params.iter().map(|(name, _)| UniformDesc::new(name, UniformType::Float2)).collect(),
+        },
+    };
+    let shader = Shader::new(ctx, vertex_shader, desc.fragment_shader, meta)?;
+
+    let pipeline = Pipeline::new(
+        ctx,
+        &[BufferLayout::default()],
+        &[
+            VertexAttribute::new("pos", VertexFormat::Float2),
+            VertexAttribute::new("uv", VertexFormat::Float2),
+        ],
+        shader);
+    resources.push(pipeline.into_any());
+    resources.push(shader.into_any());
+    Ok(pipeline)
+}
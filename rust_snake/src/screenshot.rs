@@ -0,0 +1,92 @@
+use std::time::SystemTime;
+
+use winopengl::GraphicsContext;
+
+/// Save the frame currently in the framebuffer as `screenshot_<unix time in ms>.bmp`.
+/// Has to be called at the end of the draw, before the buffers are swapped.
+pub fn save(ctx: &mut GraphicsContext) -> std::io::Result<String> {
+    let (width, height) = ctx.screen_size();
+    let pixels = ctx.read_pixels();
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let file_name = format!("screenshot_{}.bmp", timestamp);
+    write_bmp(&file_name, width as u32, height as u32, &pixels)?;
+    Ok(file_name)
+}
+
+/// Write top-down RGBA rows as a BMP, alpha is dropped
+pub fn write_bmp(path: &str, width: u32, height: u32, pixels: &[u8]) -> std::io::Result<()> {
+    let mut image = bmp::Image::new(width, height);
+    for (i, p) in pixels.chunks_exact(4).enumerate() {
+        let x = i as u32 % width;
+        let y = i as u32 / width;
+        image.set_pixel(x, y, bmp::Pixel::new(p[0], p[1], p[2]));
+    }
+    image.save(path)
+}
+
+/// Read a BMP back as `(width, height, top-down RGBA rows)`, fully opaque
+#[cfg(test)]
+pub fn read_bmp(path: &str) -> Result<(u32, u32, Vec<u8>), String> {
+    let image = bmp::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    let (width, height) = (image.get_width(), image.get_height());
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let p = image.get_pixel(x, y);
+            pixels.extend_from_slice(&[p.r, p.g, p.b, 255]);
+        }
+    }
+    Ok((width, height, pixels))
+}
+
+/// Golden image check between two RGBA frames of the same size.
+/// A pixel differs when one of its color channels is off by more than `tolerance`,
+/// alpha is ignored since BMP files do not keep it.
+/// Returns how many pixels differ, or an error when there are more than `max_differing`.
+#[cfg(test)]
+pub fn compare(expected: &[u8], actual: &[u8], tolerance: u8, max_differing: usize) -> Result<usize, String> {
+    if expected.len() != actual.len() {
+        return Err(format!("size mismatch: {} bytes expected, got {}", expected.len(), actual.len()));
+    }
+    let differing = expected
+        .chunks_exact(4)
+        .zip(actual.chunks_exact(4))
+        .filter(|(e, a)| e[..3].iter().zip(&a[..3]).any(|(e, a)| e.abs_diff(*a) > tolerance))
+        .count();
+    if differing > max_differing {
+        return Err(format!(
+            "{} pixels differ by more than {}, at most {} allowed",
+            differing, tolerance, max_differing
+        ));
+    }
+    Ok(differing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_counts_pixels_past_the_tolerance() {
+        let expected = [10, 20, 30, 255, 0, 0, 0, 255, 100, 100, 100, 255];
+        let actual = [12, 18, 30, 0, 0, 0, 9, 255, 100, 100, 100, 255];
+        assert_eq!(compare(&expected, &actual, 2, 1), Ok(1));
+        assert_eq!(compare(&expected, &actual, 9, 0), Ok(0));
+        assert!(compare(&expected, &actual, 1, 1).is_err());
+        assert!(compare(&expected, &actual[..8], 255, 3).is_err());
+    }
+
+    #[test]
+    fn bmp_round_trip() {
+        let pixels = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 7, 8, 9, 255, 1, 2, 3, 255, 4, 5, 6, 255];
+        let path = std::env::temp_dir().join("rust_snake_round_trip.bmp");
+        let path = path.to_str().unwrap();
+        write_bmp(path, 3, 2, &pixels).unwrap();
+        assert_eq!(read_bmp(path), Ok((3, 2, pixels.to_vec())));
+        let _ = std::fs::remove_file(path);
+    }
+}
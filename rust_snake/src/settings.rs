@@ -0,0 +1,85 @@
+use std::{collections::HashMap, fs};
+
+/// Player settings, read from `./settings.txt` if it exists.
+/// One `name=value` per line, `#` starts a comment:
+///
+/// ```text
+/// crt=off
+/// bloom=on
+/// board_width=30
+/// post=bloom,crt
+/// crt.scanlines=600,0.4
+/// ```
+pub struct Settings
+{
+    values: HashMap<String, String>,
+}
+
+impl Settings {
+    pub fn load() -> Settings
+    {
+        Settings::parse(&fs::read_to_string("./settings.txt").unwrap_or_default())
+    }
+
+    /// Same as `load`, from the content of a settings file
+    pub fn parse(content: &str) -> Settings
+    {
+        let mut values = HashMap::new();
+        for line in content.lines()
+        {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if let Some((name, value)) = line.split_once('=')
+            {
+                values.insert(name.trim().to_lowercase(), value.trim().to_lowercase());
+            }
+        }
+        Settings { values }
+    }
+
+    /// `on`/`off` switch, `default` when missing or unreadable
+    pub fn enabled(&self, name: &str, default: bool) -> bool
+    {
+        match self.values.get(name).map(|v| v.as_str()) {
+            Some("on") | Some("true") | Some("1") => true,
+            Some("off") | Some("false") | Some("0") => false,
+            _ => default,
+        }
+    }
+
+    /// Integer value, `default` when missing or unreadable
+    pub fn number(&self, name: &str, default: i32) -> i32
+    {
+        self.values.get(name).and_then(|v| v.parse().ok()).unwrap_or(default)
+    }
+
+    /// Comma separated values, `None` when missing
+    pub fn list(&self, name: &str) -> Option<Vec<&str>>
+    {
+        let value = self.values.get(name)?;
+        Some(value.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).collect())
+    }
+
+    /// Comma separated numbers, `None` when missing or one of them is unreadable
+    pub fn numbers(&self, name: &str) -> Option<Vec<f32>>
+    {
+        self.list(name)?.iter().map(|v| v.parse().ok()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parses_values_and_lists()
+    {
+        let settings = Settings::parse("# comment\nCRT = Off\npost=bloom, crt,,vignette\ncrt.scanlines=600,0.4 # dense\nbad=1,x");
+        assert!(!settings.enabled("crt", true));
+        assert!(settings.enabled("bloom", true));
+        assert_eq!(settings.list("post"), Some(vec!["bloom", "crt", "vignette"]));
+        assert_eq!(settings.numbers("crt.scanlines"), Some(vec![600., 0.4]));
+        assert_eq!(settings.numbers("bad"), None);
+        assert_eq!(settings.list("missing"), None);
+    }
+}
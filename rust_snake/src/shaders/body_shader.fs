@@ -7,7 +7,7 @@ void main() {
     lowp float min = 0.1;
     
     lowp vec4 res = texture2D(tex, texcoord);
-    if (res.r > max)
+    if (res.a < 0.5)
     {
         discard;
     }
@@ -0,0 +1,24 @@
+#version 100
+precision mediump float;
+varying lowp vec2 texcoord;
+uniform sampler2D tex;
+// only what was drawn in the glow pass (the bonuses) blooms
+uniform sampler2D glow;
+// size of one pixel in uv
+uniform vec2 texel;
+// x: intensity, y: spread in pixels
+uniform vec2 bloom;
+
+void main() {
+    vec4 res = texture2D(tex, texcoord);
+    vec3 sum = vec3(0.0);
+    for (int i = -2; i <= 2; i++) {
+        for (int j = -2; j <= 2; j++) {
+            vec2 offset = vec2(float(i), float(j)) * texel * bloom.y;
+            float weight = 1.0 - length(vec2(float(i), float(j))) / 3.0;
+            sum += texture2D(glow, texcoord + offset).rgb * weight;
+        }
+    }
+    res.rgb += sum / 10.0 * bloom.x;
+    gl_FragColor = res;
+}
@@ -0,0 +1,13 @@
+#version 100
+precision mediump float;
+varying lowp vec2 texcoord;
+uniform sampler2D tex;
+// x: number of scanlines, y: how dark the gaps between lines get
+uniform vec2 scanlines;
+
+void main() {
+    vec4 res = texture2D(tex, texcoord);
+    float line = 0.5 + 0.5 * sin(texcoord.y * scanlines.x * 3.14159);
+    res.rgb *= 1.0 - scanlines.y * (1.0 - line);
+    gl_FragColor = res;
+}
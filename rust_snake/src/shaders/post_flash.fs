@@ -0,0 +1,12 @@
+#version 100
+precision mediump float;
+varying lowp vec2 texcoord;
+uniform sampler2D tex;
+// x: 0 no flash, 1 full white
+uniform vec2 flash;
+
+void main() {
+    vec4 res = texture2D(tex, texcoord);
+    res.rgb = mix(res.rgb, vec3(1.0, 1.0, 1.0), flash.x);
+    gl_FragColor = res;
+}
@@ -0,0 +1,13 @@
+#version 100
+precision mediump float;
+varying lowp vec2 texcoord;
+uniform sampler2D tex;
+// x: 0 full color, 1 full gray
+uniform vec2 grayscale;
+
+void main() {
+    vec4 res = texture2D(tex, texcoord);
+    float luma = dot(res.rgb, vec3(0.299, 0.587, 0.114));
+    res.rgb = mix(res.rgb, vec3(luma, luma, luma), grayscale.x);
+    gl_FragColor = res;
+}
@@ -0,0 +1,13 @@
+#version 100
+precision mediump float;
+varying lowp vec2 texcoord;
+uniform sampler2D tex;
+// x: radius where darkening starts, y: width of the fade
+uniform vec2 vignette;
+
+void main() {
+    vec4 res = texture2D(tex, texcoord);
+    float dist = distance(texcoord, vec2(0.5, 0.5));
+    res.rgb *= smoothstep(vignette.x + vignette.y, vignette.x, dist);
+    gl_FragColor = res;
+}
@@ -1,9 +1,9 @@
 #version 100
 attribute vec2 pos;
 attribute vec2 uv;
-uniform vec2 offset;
+uniform mat4 transform;
 varying lowp vec2 texcoord;
 void main() {
-    gl_Position = vec4(pos + offset, 0, 1);
+    gl_Position = transform * vec4(pos, 0, 1);
     texcoord = uv;
 }
\ No newline at end of file
@@ -0,0 +1,35 @@
+use winopengl::{AtlasBuilder, GraphicsContext, Sprite, TextureAtlas, Transform2D};
+
+use crate::{font, images::bmp::{SNAKE_HEAD, SNAKE_BODY, SNAKE_BONUS}, pos::Pos, texture::get_texture};
+
+pub const WHITE: (u8, u8, u8, u8) = (255, 255, 255, 255);
+
+//Tous les sprites du jeu et la police de debug sont dans une seule texture, seul le fond garde la sienne
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SpriteId
+{
+    Head,
+    Body,
+    Bonus,
+    Glyph(char),
+    //Bloc blanc des rectangles de la police
+    Solid
+}
+
+pub fn build_atlas(ctx: &mut GraphicsContext) -> TextureAtlas<SpriteId>
+{
+    let mut builder = AtlasBuilder::new();
+    for (id, file) in [(SpriteId::Head, SNAKE_HEAD), (SpriteId::Body, SNAKE_BODY), (SpriteId::Bonus, SNAKE_BONUS)]
+    {
+        let texture = get_texture(file);
+        builder.add(id, texture.width as _, texture.height as _, &texture.data);
+    }
+    font::add_to_atlas(&mut builder);
+    builder.build(ctx)
+}
+
+//Sprite centre sur la case, le monde est en cases et un sprite en couvre une quelle que soit sa taille
+pub fn get_transform(pos: Pos, sprite: Sprite) -> Transform2D {
+    Transform2D::from_translation(f32::from(pos.x) + 0.5, f32::from(pos.y) + 0.5)
+        .scaled(1. / sprite.size.0, 1. / sprite.size.1)
+}
@@ -1,35 +1,59 @@
-
 pub struct TextureData
 {    
     pub width: u16,
     pub height: u16,
+    /// RGBA8
     pub data: Vec<u8>,
 }
+impl TextureData {
+    pub fn upload(&self, ctx: &mut GraphicsContext) -> Texture
+    {
+        Texture::new(ctx, &self.data, TextureParams {
+            width: self.width as _,
+            height: self.height as _,
+            ..Default::default()
+        })
+    }
+}
 extern crate bmp;
 
+use std::io::Cursor;
+
+use winopengl::{GraphicsContext, Texture, TextureParams};
+
+//Couleur transparente des sprites
+const COLOR_KEY: (u8, u8, u8) = (255, 0, 0);
+
 pub fn get_texture(file_data: &[u8]) -> TextureData {
-    let file = bmp::open("./images/SnakeHeadBorder.bmp"); 
+    let file = bmp::from_reader(&mut Cursor::new(file_data)); 
     let pixels = file.unwrap();
     
     //LOADING IMAGE;
     let width = pixels.get_width();
     let height = pixels.get_height();
 
+    //Ligne par ligne, en commencant par le haut
     let mut data = Vec::new();
-    for i in 0..width
+    for j in 0..height
     {
-        for j in 0..height
+        for i in 0..width
         {
             let p = pixels.get_pixel(i, j);
-            data.push(p.r);
-            data.push(p.g);
-            data.push(p.b);
+            //Noir transparent pour que le filtrage ne bave pas de rouge sur les bords
+            if (p.r, p.g, p.b) == COLOR_KEY
+            {
+                data.extend_from_slice(&[0, 0, 0, 0]);
+            }
+            else
+            {
+                data.extend_from_slice(&[p.r, p.g, p.b, 255]);
+            }
         }
     }
     TextureData
     {
         width: width as _,
         height: height as _,
-        data: data
+        data
     }
 }
@@ -1,5 +1,75 @@
+/// Kind of OpenGL context to ask for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlProfile {
+    /// Desktop GL with the whole legacy API
+    Compatibility,
+    /// Desktop GL without the deprecated functions, shaders must use `#version 140` or later
+    Core,
+    /// OpenGL ES
+    Es,
+}
+
+/// How the main loop paces update/draw
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopMode {
+    /// One frame after the other, only limited by `Conf::swap_interval`
+    Continuous,
+    /// Sleep after each frame to run at most this many frames per second
+    TargetFps(u32),
+    /// Sleep until an input event, a resize, or the time given to `GraphicsContext::schedule_update`
+    WaitEvents,
+}
+
 pub struct Conf {
     pub window_title: String,
     pub window_width: i32,
     pub window_height: i32,
-}
\ No newline at end of file
+    /// Let the user resize the window, the new size comes through `EventHandler::resize_event`
+    pub window_resizable: bool,
+    /// Start as a borderless window covering the whole monitor, see `GraphicsContext::set_fullscreen`
+    pub fullscreen: bool,
+    /// Render at the monitor real pixel density instead of letting the system upscale the window.
+    /// `window_width`/`window_height` are then logical pixels, multiplied by `GraphicsContext::dpi_scale`.
+    pub high_dpi: bool,
+    /// Vertical blanks to wait for between two frames: 1 is vsync, 0 renders as fast as possible
+    pub swap_interval: i32,
+    /// MSAA samples of the default framebuffer, 1 or less for none
+    pub sample_count: i32,
+    /// (major, minor) version of the context to create first.
+    /// When the driver refuses it the backends fall back to GLES 2.0 for `GlProfile::Es`, then GL 2.1 compatibility,
+    /// `GraphicsContext::context_info` tells what was created.
+    pub gl_version: (i32, i32),
+    pub gl_profile: GlProfile,
+    /// Report GL errors through `gl::set_debug_hook`, stderr by default.
+    /// Uses the `KHR_debug` callback when the context has it, otherwise checks `glGetError` after every GL call,
+    /// which is slow but names the failing call and its arguments.
+    /// Also asks GLX, WGL and EGL 1.5 for a debug context, where drivers report more than errors.
+    pub gl_debug: bool,
+    /// Ignored by `headless_frames`, which renders its frames back to back
+    pub loop_mode: LoopMode,
+    /// Render offscreen for this many frames instead of opening a window, then return.
+    /// Uses an EGL pbuffer, so it works without a display server or a GPU (Mesa llvmpipe).
+    /// The context is always OpenGL ES: `gl_version` is used with `GlProfile::Es`, other profiles get GLES 2.0
+    /// and `GraphicsContext::context_info` reports them as fallen back.
+    pub headless_frames: Option<u32>,
+}
+
+impl Default for Conf {
+    fn default() -> Conf {
+        Conf {
+            window_title: "".to_owned(),
+            window_width: 800,
+            window_height: 600,
+            window_resizable: false,
+            fullscreen: false,
+            high_dpi: false,
+            swap_interval: 1,
+            sample_count: 1,
+            gl_version: (2, 1),
+            gl_profile: GlProfile::Compatibility,
+            gl_debug: false,
+            loop_mode: LoopMode::Continuous,
+            headless_frames: None,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+use crate::{gl::GlLoadError, FramebufferError, ShaderError};
+
+/// Why `start` could not run the application
+#[derive(Debug)]
+pub enum Error {
+    /// The window system or its library is not available, or refused the window
+    Window(String),
+    /// No pixel format or framebuffer config matches the one asked
+    PixelFormat(String),
+    /// The GL library is missing, or no context could be created and made current
+    Context(String),
+    /// The context lacks GL functions winopengl needs
+    Loader(GlLoadError),
+    /// A shader of the event handler failed to build
+    Shader(ShaderError),
+    /// A render target of the event handler is not supported by the driver
+    Framebuffer(FramebufferError),
+}
+
+impl From<GlLoadError> for Error {
+    fn from(e: GlLoadError) -> Error {
+        Error::Loader(e)
+    }
+}
+
+impl From<ShaderError> for Error {
+    fn from(e: ShaderError) -> Error {
+        Error::Shader(e)
+    }
+}
+
+impl From<FramebufferError> for Error {
+    fn from(e: FramebufferError) -> Error {
+        Error::Framebuffer(e)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Window(message) => write!(f, "failed to create the window: {}", message),
+            Error::PixelFormat(message) => write!(f, "no suitable pixel format: {}", message),
+            Error::Context(message) => write!(f, "failed to create the OpenGL context: {}", message),
+            Error::Loader(e) => write!(f, "failed to load OpenGL: {}", e),
+            Error::Shader(e) => write!(f, "failed to build a shader: {}", e),
+            Error::Framebuffer(e) => write!(f, "failed to create a render target: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Loader(e) => Some(e),
+            Error::Shader(e) => Some(e),
+            Error::Framebuffer(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -8,11 +8,31 @@ pub enum KeyCode {
     Left,
     Down,
     Up,
+    F3,
+    F11,
+    F12,
+    PageUp,
+    PageDown,
+    Enter,
+    Backspace,
+    Tab,
+    /// Key left of 1, whatever the layout prints on it
+    GraveAccent,
     Unknown,
 }
 
 pub trait EventHandler {
-    fn update(&mut self, _ctx: &mut GraphicsContext);
+    /// `dt` is the time in seconds since the previous update
+    fn update(&mut self, _ctx: &mut GraphicsContext, _dt: f32);
     fn draw(&mut self, _ctx: &mut GraphicsContext);
+    /// Sent again at the system auto-repeat rate while the key is held down
     fn key_down_event(&mut self, _ctx: &mut GraphicsContext, _keycode: KeyCode) {}
+    /// Sent once when the key is released, auto-repeat included
+    fn key_up_event(&mut self, _ctx: &mut GraphicsContext, _keycode: KeyCode) {}
+    /// Text typed, after the keyboard layout, control characters excluded
+    fn char_event(&mut self, _ctx: &mut GraphicsContext, _character: char) {}
+    /// Cursor position in window pixels, origin at the top left and y going down
+    fn mouse_motion_event(&mut self, _ctx: &mut GraphicsContext, _x: f32, _y: f32) {}
+    /// The framebuffer changed size, `GraphicsContext::screen_size` already returns the new one
+    fn resize_event(&mut self, _ctx: &mut GraphicsContext, _width: i32, _height: i32) {}
 }
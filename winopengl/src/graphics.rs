@@ -1,14 +1,35 @@
 use std::{ffi::CString, mem};
+mod atlas;
+mod camera;
+mod resources;
+mod sprite_batch;
+mod stats;
 mod texture;
+mod transform;
 use std::{error::Error, fmt::Display};
-pub use texture::{Texture};
+pub use atlas::{AtlasBuilder, Sprite, TextureAtlas};
+pub use camera::Camera2D;
+pub use resources::{AnyResource, Id, Owned, Resource};
+pub use sprite_batch::SpriteBatch;
+pub use stats::{FrameCounters, Stats};
+use stats::GpuTimer;
+use resources::{DropQueue, Slots};
+pub use texture::{FilterMode, Texture, TextureFormat, TextureParams, TextureWrap};
+use texture::TextureInternal;
+pub use transform::{mat4_mul, ortho, Mat4, Transform2D, IDENTITY};
 use crate::graphics::GraphicsContext as Context;
+use crate::conf::GlProfile;
 use crate::gl::{
     GLuint, 
     GLint, 
     GLenum, 
     glGetUniformLocation, 
     glBindBuffer, 
+    glDeleteBuffers,
+    glDeleteFramebuffers,
+    glDeleteProgram,
+    glDeleteShader,
+    glDetachShader,
     glActiveTexture, 
     glBindTexture, 
     glGetIntegerv, 
@@ -38,13 +59,35 @@ use crate::gl::{
     glAttachShader, 
     glCreateProgram, 
     glLinkProgram,
-    glUniform2fv, 
+    glUniform1fv,
+    glUniform2fv,
+    glUniform3fv,
+    glUniform4fv,
+    glUniform1iv,
+    glUniform2iv,
+    glUniform3iv,
+    glUniform4iv,
+    glUniformMatrix4fv,
+    glBlendFuncSeparate,
+    glBlendEquationSeparate,
+    glDepthFunc,
+    glDepthMask,
+    glCullFace,
+    glColorMask,
     glDrawElementsInstanced,  
     glClearDepthf, 
+    glClearDepth,
+    glDrawElements,
+    glGetStringi,
     glClear, 
     glViewport, 
     glBindFramebuffer,  
+    glGenFramebuffers,
+    glFramebufferTexture2D,
+    glCheckFramebufferStatus,
     glClearColor, 
+    glPixelStorei,
+    glReadPixels,
     GL_FLOAT,
     GL_ARRAY_BUFFER, 
     GL_TEXTURE0, 
@@ -53,10 +96,43 @@ use crate::gl::{
     GL_FRAMEBUFFER_BINDING, 
     GL_SCISSOR_TEST, 
     GL_DEPTH_TEST, 
-    GL_CCW, GL_FALSE,
+    GL_CCW,
+    GL_CW,
+    GL_SHORT,
+    GL_BLEND,
+    GL_CULL_FACE,
+    GL_FRONT,
+    GL_BACK,
+    GL_NEVER,
+    GL_LESS,
+    GL_EQUAL,
+    GL_LEQUAL,
+    GL_GREATER,
+    GL_NOTEQUAL,
+    GL_GEQUAL,
+    GL_ALWAYS,
+    GL_FUNC_ADD,
+    GL_FUNC_SUBTRACT,
+    GL_FUNC_REVERSE_SUBTRACT,
+    GL_ZERO,
+    GL_ONE,
+    GL_SRC_COLOR,
+    GL_ONE_MINUS_SRC_COLOR,
+    GL_SRC_ALPHA,
+    GL_ONE_MINUS_SRC_ALPHA,
+    GL_DST_ALPHA,
+    GL_ONE_MINUS_DST_ALPHA,
+    GL_DST_COLOR,
+    GL_ONE_MINUS_DST_COLOR,
+    GL_SRC_ALPHA_SATURATE,
+    GL_POINTS,
+    GL_LINES,
     GL_COLOR_BUFFER_BIT,
     GL_DEPTH_BUFFER_BIT,
     GL_FRAMEBUFFER,
+    GL_FRAMEBUFFER_COMPLETE,
+    GL_COLOR_ATTACHMENT0,
+    GL_DEPTH_ATTACHMENT,
     GL_TRIANGLES, 
     GL_UNSIGNED_SHORT, 
     GL_VERTEX_SHADER, 
@@ -64,9 +140,12 @@ use crate::gl::{
     GL_LINK_STATUS, 
     GL_INFO_LOG_LENGTH,
     GL_COMPILE_STATUS, 
-    GL_STATIC_DRAW};
+    GL_STATIC_DRAW,
+    GL_STREAM_DRAW,
+    GL_PACK_ALIGNMENT,
+    GL_RGBA,
+    GL_UNSIGNED_BYTE};
 
-const FLOAT2_SIZE:usize = 8;
 
 fn get_uniform_location(program: GLuint, name: &str) -> Option<i32> {
     let cname = CString::new(name).unwrap_or_else(|e| panic!("{}", e));
@@ -79,9 +158,49 @@ fn get_uniform_location(program: GLuint, name: &str) -> Option<i32> {
     Some(location)
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UniformType {
+    /// One 32-bit wide float (equivalent to `f32`)
+    Float1,
+    /// Two 32-bit wide floats (equivalent to `[f32; 2]`)
+    Float2,
+    /// Three 32-bit wide floats (equivalent to `[f32; 3]`)
+    Float3,
+    /// Four 32-bit wide floats (equivalent to `[f32; 4]`)
+    Float4,
+    /// One signed 32-bit integer (equivalent to `i32`)
+    Int1,
+    /// Two signed 32-bit integers (equivalent to `[i32; 2]`)
+    Int2,
+    /// Three signed 32-bit integers (equivalent to `[i32; 3]`)
+    Int3,
+    /// Four signed 32-bit integers (equivalent to `[i32; 4]`)
+    Int4,
+    /// Four by four matrix of 32-bit floats, column major
+    Mat4,
+}
+
+impl UniformType {
+    /// Byte size for a given UniformType
+    pub fn size(&self) -> usize {
+        match self {
+            UniformType::Float1 => 4,
+            UniformType::Float2 => 8,
+            UniformType::Float3 => 12,
+            UniformType::Float4 => 16,
+            UniformType::Int1 => 4,
+            UniformType::Int2 => 8,
+            UniformType::Int3 => 12,
+            UniformType::Int4 => 16,
+            UniformType::Mat4 => 64,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct UniformDesc {
     name: String,
+    uniform_type: UniformType,
     array_count: usize,
 }
 
@@ -91,12 +210,21 @@ pub struct UniformBlockLayout {
 }
 
 impl UniformDesc {
-    pub fn new(name: &str) -> UniformDesc {
+    pub fn new(name: &str, uniform_type: UniformType) -> UniformDesc {
         UniformDesc {
             name: name.to_string(),
+            uniform_type,
             array_count: 1,
         }
     }
+
+    /// `uniform_type name[array_count]` in the shader
+    pub fn array(self, array_count: usize) -> UniformDesc {
+        UniformDesc {
+            array_count,
+            ..self
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -105,35 +233,136 @@ pub struct ShaderMeta {
     pub images: Vec<String>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub enum VertexStep {
+    #[default]
     PerVertex,
     PerInstance,
 }
 
-impl Default for VertexStep {
-    fn default() -> VertexStep {
-        VertexStep::PerVertex
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VertexFormat {
+    /// One 32-bit float
+    Float1,
+    /// Two 32-bit floats
+    Float2,
+    /// Three 32-bit floats
+    Float3,
+    /// Four 32-bit floats
+    Float4,
+    /// Four unsigned bytes, normalized to 0..1 in the shader (colors)
+    Byte4,
+    /// Two unsigned shorts, normalized to 0..1 in the shader (texture coordinates)
+    UShort2,
+    /// Four unsigned shorts, normalized to 0..1 in the shader
+    UShort4,
+    /// Two signed shorts, read as plain numbers
+    Short2,
+    /// Four signed shorts, read as plain numbers
+    Short4,
+    /// Four by four float matrix, takes four consecutive attribute locations
+    Mat4,
+}
+
+impl VertexFormat {
+    /// Number of components in each attribute location
+    pub fn components(&self) -> i32 {
+        match self {
+            VertexFormat::Float1 => 1,
+            VertexFormat::Float2 => 2,
+            VertexFormat::Float3 => 3,
+            VertexFormat::Float4 => 4,
+            VertexFormat::Byte4 => 4,
+            VertexFormat::UShort2 => 2,
+            VertexFormat::UShort4 => 4,
+            VertexFormat::Short2 => 2,
+            VertexFormat::Short4 => 4,
+            VertexFormat::Mat4 => 4,
+        }
+    }
+
+    /// Number of attribute locations the format takes
+    pub fn locations(&self) -> i32 {
+        match self {
+            VertexFormat::Mat4 => 4,
+            _ => 1,
+        }
+    }
+
+    /// Size in bytes of one component
+    pub fn component_size(&self) -> i32 {
+        match self {
+            VertexFormat::Byte4 => 1,
+            VertexFormat::UShort2
+            | VertexFormat::UShort4
+            | VertexFormat::Short2
+            | VertexFormat::Short4 => 2,
+            _ => 4,
+        }
+    }
+
+    /// Size in bytes of the whole attribute
+    pub fn size_bytes(&self) -> i32 {
+        self.components() * self.component_size() * self.locations()
+    }
+
+    fn type_(&self) -> GLenum {
+        match self {
+            VertexFormat::Byte4 => GL_UNSIGNED_BYTE,
+            VertexFormat::UShort2 | VertexFormat::UShort4 => GL_UNSIGNED_SHORT,
+            VertexFormat::Short2 | VertexFormat::Short4 => GL_SHORT,
+            _ => GL_FLOAT,
+        }
+    }
+
+    fn normalized(&self) -> bool {
+        matches!(
+            self,
+            VertexFormat::Byte4 | VertexFormat::UShort2 | VertexFormat::UShort4
+        )
+    }
+}
+
+/// How one vertex buffer is laid out
+#[derive(Clone, Copy, Debug)]
+pub struct BufferLayout {
+    /// Bytes between two elements, 0 to compute it from the attributes using the buffer
+    pub stride: i32,
+    pub step_func: VertexStep,
+    /// With `VertexStep::PerInstance`, how many instances share one element
+    pub step_rate: i32,
+}
+
+impl Default for BufferLayout {
+    fn default() -> BufferLayout {
+        BufferLayout {
+            stride: 0,
+            step_func: VertexStep::PerVertex,
+            step_rate: 1,
+        }
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct VertexAttribute {
     pub name: &'static str,
+    pub format: VertexFormat,
     pub buffer_index: usize,
 }
 
 impl VertexAttribute {
-    pub const fn new(name: &'static str) -> VertexAttribute {
-        Self::with_buffer(name, 0)
+    pub const fn new(name: &'static str, format: VertexFormat) -> VertexAttribute {
+        Self::with_buffer(name, format, 0)
     }
 
     pub const fn with_buffer(
         name: &'static str,
+        format: VertexFormat,
         buffer_index: usize,
     ) -> VertexAttribute {
         VertexAttribute {
             name,
+            format,
             buffer_index,
         }
     }
@@ -164,7 +393,13 @@ impl From<std::ffi::NulError> for ShaderError {
 
 impl Display for ShaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self) // Display the same way as Debug
+        match self {
+            ShaderError::CompilationError { shader_type, error_message } => {
+                write!(f, "{:?} shader failed to compile:\n{}", shader_type, error_message.trim_end())
+            }
+            ShaderError::LinkError(message) => write!(f, "shader failed to link:\n{}", message.trim_end()),
+            ShaderError::FFINulError(e) => write!(f, "shader source is not a valid C string: {}", e),
+        }
     }
 }
 
@@ -174,8 +409,8 @@ impl Error for ShaderError {
     }
 }
 
-#[derive(Clone, Debug, Copy)]
-pub struct Shader(usize);
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub struct Shader(Id);
 
 impl Shader {
     pub fn new(
@@ -185,8 +420,15 @@ impl Shader {
         meta: ShaderMeta,
     ) -> Result<Shader, ShaderError> {
         let shader = load_shader_internal(vertex_shader, fragment_shader, meta)?;
-        ctx.shaders.push(shader);
-        Ok(Shader(ctx.shaders.len() - 1))
+        Ok(Shader(ctx.shaders.insert(shader)))
+    }
+
+    /// Free the GL program, pipelines using it can't be applied anymore
+    pub fn delete(self, ctx: &mut Context) {
+        let shader = ctx.shaders.remove(self.0);
+        unsafe {
+            glDeleteProgram(shader.program);
+        }
     }
 }
 
@@ -200,8 +442,8 @@ pub struct ShaderImage {
 #[derive(Debug)]
 pub struct ShaderUniform {
     gl_loc: UniformLocation,
-    _offset: usize,
-    _size: usize,
+    offset: usize,
+    uniform_type: UniformType,
     array_count: i32,
 }
 
@@ -209,6 +451,8 @@ struct ShaderInternal {
     program: GLuint,
     images: Vec<ShaderImage>,
     uniforms: Vec<ShaderUniform>,
+    /// Bytes the uniforms struct must have
+    uniforms_size: usize,
 }
 
 #[derive(Default, Copy, Clone)]
@@ -226,6 +470,13 @@ struct GlCache {
     textures: [GLuint; MAX_SHADERSTAGE_IMAGES],
     cur_pipeline: Option<Pipeline>,
     attributes: [Option<CachedAttribute>; MAX_VERTEX_ATTRIBUTES],
+    /// Fixed function state as last set on the GL side, `None` when disabled
+    blend: Option<(BlendState, BlendState)>,
+    depth_test: Option<Comparison>,
+    depth_write: bool,
+    cull_face: CullFace,
+    front_face_order: FrontFaceOrder,
+    color_write: ColorMask,
 }
 
 impl GlCache {
@@ -293,6 +544,130 @@ impl GlCache {
         self.index_buffer = 0;
     }
 
+    fn set_blend(&mut self, blend: Option<(BlendState, BlendState)>) {
+        if self.blend == blend {
+            return;
+        }
+        unsafe {
+            match blend {
+                None => glDisable(GL_BLEND),
+                Some((color, alpha)) => {
+                    if self.blend.is_none() {
+                        glEnable(GL_BLEND);
+                    }
+                    glBlendFuncSeparate(
+                        color.sfactor.into(),
+                        color.dfactor.into(),
+                        alpha.sfactor.into(),
+                        alpha.dfactor.into(),
+                    );
+                    glBlendEquationSeparate(color.equation.into(), alpha.equation.into());
+                }
+            }
+        }
+        self.blend = blend;
+    }
+
+    fn set_depth_test(&mut self, depth_test: Option<Comparison>) {
+        if self.depth_test == depth_test {
+            return;
+        }
+        unsafe {
+            match depth_test {
+                None => glDisable(GL_DEPTH_TEST),
+                Some(comparison) => {
+                    if self.depth_test.is_none() {
+                        glEnable(GL_DEPTH_TEST);
+                    }
+                    glDepthFunc(comparison.into());
+                }
+            }
+        }
+        self.depth_test = depth_test;
+    }
+
+    fn set_depth_write(&mut self, depth_write: bool) {
+        if self.depth_write != depth_write {
+            unsafe {
+                glDepthMask(depth_write as u8);
+            }
+            self.depth_write = depth_write;
+        }
+    }
+
+    fn set_cull_face(&mut self, cull_face: CullFace) {
+        if self.cull_face == cull_face {
+            return;
+        }
+        unsafe {
+            match cull_face {
+                CullFace::Nothing => glDisable(GL_CULL_FACE),
+                CullFace::Front | CullFace::Back => {
+                    if self.cull_face == CullFace::Nothing {
+                        glEnable(GL_CULL_FACE);
+                    }
+                    glCullFace(if cull_face == CullFace::Front { GL_FRONT } else { GL_BACK });
+                }
+            }
+        }
+        self.cull_face = cull_face;
+    }
+
+    fn set_front_face_order(&mut self, front_face_order: FrontFaceOrder) {
+        if self.front_face_order != front_face_order {
+            unsafe {
+                glFrontFace(match front_face_order {
+                    FrontFaceOrder::Clockwise => GL_CW,
+                    FrontFaceOrder::CounterClockwise => GL_CCW,
+                });
+            }
+            self.front_face_order = front_face_order;
+        }
+    }
+
+    fn set_color_write(&mut self, color_write: ColorMask) {
+        if self.color_write != color_write {
+            let (r, g, b, a) = color_write;
+            unsafe {
+                glColorMask(r as u8, g as u8, b as u8, a as u8);
+            }
+            self.color_write = color_write;
+        }
+    }
+
+    /// Drop every cached binding of a deleted buffer
+    fn forget_buffer(&mut self, gl_buf: GLuint) {
+        if self.vertex_buffer == gl_buf {
+            self.bind_buffer(GL_ARRAY_BUFFER, 0);
+        }
+        if self.index_buffer == gl_buf {
+            self.bind_buffer(GL_ELEMENT_ARRAY_BUFFER, 0);
+        }
+        if self.stored_vertex_buffer == gl_buf {
+            self.stored_vertex_buffer = 0;
+        }
+        if self.stored_index_buffer == gl_buf {
+            self.stored_index_buffer = 0;
+        }
+        for attribute in self.attributes.iter_mut() {
+            if attribute.is_some_and(|cached| cached.gl_vbuf == gl_buf) {
+                *attribute = None;
+            }
+        }
+    }
+
+    /// Drop every cached binding of a deleted texture
+    fn forget_texture(&mut self, texture: GLuint) {
+        for ix in 0..MAX_SHADERSTAGE_IMAGES {
+            if self.textures[ix] == texture {
+                self.bind_texture(ix, 0);
+            }
+        }
+        if self.stored_texture == texture {
+            self.stored_texture = 0;
+        }
+    }
+
     fn clear_texture_bindings(&mut self) {
         for ix in 0..MAX_SHADERSTAGE_IMAGES {
             if self.textures[ix] != 0 {
@@ -306,47 +681,235 @@ impl GlCache {
 pub const MAX_VERTEX_ATTRIBUTES: usize = 16;
 pub const MAX_SHADERSTAGE_IMAGES: usize = 12;
 
+/// What the context can do beyond GL 2.1 / GLES 2.0, detected at startup
+#[derive(Clone, Debug)]
 pub struct Features {
+    /// Instanced draws and per instance attributes, `draw` ignores `num_instances` above 1 otherwise
     pub instancing: bool,
+    /// Vertex array objects, required by core profiles
+    pub vertex_arrays: bool,
+    /// `GL_TIME_ELAPSED` queries, see `GraphicsContext::enable_gpu_timer`
+    pub timer_query: bool,
+    /// `KHR_debug` callback, see `Conf::gl_debug`
+    pub debug_output: bool,
+    /// `TextureFormat::Alpha8` is stored as `GL_R8` swizzled to alpha, since core profiles
+    /// have no `GL_ALPHA`. GL 3.3, GLES 3.0 or `ARB_texture_swizzle`, `GL_ALPHA` is used otherwise
+    pub red_textures: bool,
+    /// `GL_MAX_VERTEX_ATTRIB_STRIDE`, `None` before GL 4.4 and GLES 3.1 which do not report it
+    pub max_vertex_attrib_stride: Option<i32>,
+    /// Optional GL functions the driver does not have
+    pub missing_functions: Vec<&'static str>,
+}
+
+impl Features {
+    pub(crate) fn detect(info: &ContextInfo, missing_functions: Vec<&'static str>) -> Features {
+        use crate::gl::loaded;
+
+        let es = info.profile == GlProfile::Es;
+        let gl3 = info.version.0 >= 3;
+        Features {
+            instancing: gl3 && loaded::glDrawElementsInstanced() && loaded::glVertexAttribDivisor(),
+            vertex_arrays: (gl3 || info.has_extension("GL_ARB_vertex_array_object"))
+                && loaded::glGenVertexArrays()
+                && loaded::glBindVertexArray(),
+            // GLES only has them as EXT_disjoint_timer_query, under other names
+            timer_query: !es
+                && (info.version >= (3, 3) || info.has_extension("GL_ARB_timer_query"))
+                && loaded::glGenQueries()
+                && loaded::glDeleteQueries()
+                && loaded::glBeginQuery()
+                && loaded::glEndQuery()
+                && loaded::glGetQueryObjectiv()
+                && loaded::glGetQueryObjectui64v(),
+            debug_output: info.version >= if es { (3, 2) } else { (4, 3) } || info.has_extension("GL_KHR_debug"),
+            red_textures: gl3 && (info.version >= if es { (3, 0) } else { (3, 3) } || info.has_extension("GL_ARB_texture_swizzle")),
+            max_vertex_attrib_stride: if info.version >= if es { (3, 1) } else { (4, 4) } {
+                let mut stride = 0;
+                unsafe { glGetIntegerv(crate::gl::GL_MAX_VERTEX_ATTRIB_STRIDE, &mut stride) };
+                Some(stride)
+            } else {
+                None
+            },
+            missing_functions,
+        }
+    }
+}
+
+/// The context the backend ended up creating, which may differ from what `Conf` asked for
+#[derive(Clone, Debug)]
+pub struct ContextInfo {
+    /// `GL_VERSION` as reported by the driver
+    pub version_string: String,
+    /// `GL_RENDERER`, the GPU or software rasterizer name
+    pub renderer: String,
+    /// (major, minor), parsed from `version_string`
+    pub version: (i32, i32),
+    pub profile: GlProfile,
+    /// MSAA samples of the default framebuffer, 0 without multisampling
+    pub samples: i32,
+    /// `None` when the platform has no way to set it and the driver default applies
+    pub swap_interval: Option<i32>,
+    /// The requested `Conf::gl_version`/`Conf::gl_profile` was refused and the fallback context is used
+    pub fell_back: bool,
+    /// `GL_EXTENSIONS`, one name per entry
+    pub extensions: Vec<String>,
+}
+
+impl Default for ContextInfo {
+    fn default() -> ContextInfo {
+        ContextInfo {
+            version_string: String::new(),
+            renderer: String::new(),
+            version: (2, 1),
+            profile: GlProfile::Compatibility,
+            samples: 0,
+            swap_interval: None,
+            fell_back: false,
+            extensions: Vec::new(),
+        }
+    }
+}
+
+impl ContextInfo {
+    /// Read back from the current context what the driver does not tell at creation
+    pub(crate) unsafe fn query(profile: GlProfile, swap_interval: Option<i32>, fell_back: bool) -> ContextInfo {
+        let gl_string = |name| {
+            let string = crate::gl::glGetString(name);
+            if string.is_null() {
+                String::new()
+            } else {
+                std::ffi::CStr::from_ptr(string as _).to_string_lossy().into_owned()
+            }
+        };
+        let version_string = gl_string(crate::gl::GL_VERSION);
+        let mut samples = 0;
+        glGetIntegerv(crate::gl::GL_SAMPLES, &mut samples);
+
+        let is_es = version_string.starts_with("OpenGL ES");
+        // "4.6 (Core Profile) Mesa 23.1" or "OpenGL ES 3.2 Mesa 23.1"
+        let mut numbers = version_string
+            .trim_start_matches("OpenGL ES ")
+            .trim_start_matches("OpenGL ES-CM ")
+            .split(|c: char| !c.is_ascii_digit())
+            .map(|n| n.parse().unwrap_or(0));
+        let version = (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0));
+
+        // GL 3 lists them one by one, the single string is gone from core profiles
+        let extensions = if version.0 >= 3 && crate::gl::loaded::glGetStringi() {
+            let mut count = 0;
+            glGetIntegerv(crate::gl::GL_NUM_EXTENSIONS, &mut count);
+            (0..count.max(0) as GLuint)
+                .map(|i| glGetStringi(crate::gl::GL_EXTENSIONS, i))
+                .filter(|e| !e.is_null())
+                .map(|e| std::ffi::CStr::from_ptr(e as _).to_string_lossy().into_owned())
+                .collect()
+        } else {
+            gl_string(crate::gl::GL_EXTENSIONS)
+                .split_whitespace()
+                .map(str::to_owned)
+                .collect()
+        };
+
+        ContextInfo {
+            renderer: gl_string(crate::gl::GL_RENDERER),
+            version_string,
+            version,
+            profile: match (is_es, fell_back) {
+                (true, _) => GlProfile::Es,
+                // the desktop fallback is always a compatibility context
+                (false, true) => GlProfile::Compatibility,
+                (false, false) => profile,
+            },
+            samples,
+            swap_interval,
+            fell_back,
+            extensions,
+        }
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|e| e == name)
+    }
 }
 
-impl Default for Features {
-    fn default() -> Features {
-        Features { instancing: true }
+impl Display for ContextInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "OpenGL {}.{} {:?} on {}, {}x MSAA, swap interval {}",
+            self.version.0,
+            self.version.1,
+            self.profile,
+            self.renderer,
+            self.samples,
+            self.swap_interval.map_or("driver default".to_string(), |i| i.to_string()),
+        )?;
+        write!(f, ", {} extensions", self.extensions.len())?;
+        if self.fell_back {
+            write!(f, " (fallback, the requested version was refused)")?;
+        }
+        Ok(())
     }
 }
 
 pub struct GraphicsContext {
-    shaders: Vec<ShaderInternal>,
-    pipelines: Vec<PipelineInternal>,
+    shaders: Slots<ShaderInternal>,
+    pipelines: Slots<PipelineInternal>,
+    passes: Slots<RenderPassInternal>,
+    buffers: Slots<BufferInternal>,
+    textures: Slots<TextureInternal>,
+    drop_queue: DropQueue,
     default_framebuffer: GLuint,
     cache: GlCache,
     width:i32,
     height:i32,
+    pub(crate) dpi_scale: f32,
 
     pub(crate) features: Features,
+    pub(crate) context_info: ContextInfo,
+    /// Counters of the frame in progress
+    counters: FrameCounters,
+    /// Size of the offscreen pass bound by `begin_pass`, `None` for the default framebuffer
+    pass_size: Option<(i32, i32)>,
+    stats: Stats,
+    gpu_timer: Option<GpuTimer>,
+    /// Wakeup asked with `schedule_update`
+    pub(crate) next_update: Option<std::time::Instant>,
     pub(crate) display: Option<*mut dyn crate::NativeDisplay>,
 }
 
 impl GraphicsContext {
-    pub fn new(width:i32, height:i32) -> GraphicsContext {
+    pub(crate) fn new(width:i32, height:i32, context_info: ContextInfo, features: Features) -> GraphicsContext {
         unsafe {
             let mut default_framebuffer: GLuint = 0;
             glGetIntegerv(
                 GL_FRAMEBUFFER_BINDING,
                 &mut default_framebuffer as *mut _ as *mut _,
             );
-            let mut vao = 0;
-
-            glGenVertexArrays(1, &mut vao as *mut _);
-            glBindVertexArray(vao);
+            // without them the attributes live in the default state, as in GL 2
+            if features.vertex_arrays {
+                let mut vao = 0;
+                glGenVertexArrays(1, &mut vao as *mut _);
+                glBindVertexArray(vao);
+            }
             GraphicsContext {
                 default_framebuffer,
-                shaders: vec![],
-                pipelines: vec![],
-                features: Default::default(),
+                shaders: Slots::new("Shader"),
+                pipelines: Slots::new("Pipeline"),
+                passes: Slots::new("RenderPass"),
+                buffers: Slots::new("Buffer"),
+                textures: Slots::new("Texture"),
+                drop_queue: Default::default(),
+                features,
+                context_info,
+                next_update: None,
+                counters: Default::default(),
+                pass_size: None,
+                stats: Stats::new(),
+                gpu_timer: None,
                 width,
                 height,
+                dpi_scale: 1.,
                 cache: GlCache {
                     stored_index_buffer: 0,
                     stored_vertex_buffer: 0,
@@ -356,43 +919,199 @@ impl GraphicsContext {
                     stored_texture: 0,
                     textures: [0; MAX_SHADERSTAGE_IMAGES],
                     attributes: [None; MAX_VERTEX_ATTRIBUTES],
+                    blend: None,
+                    depth_test: None,
+                    depth_write: true,
+                    cull_face: CullFace::Nothing,
+                    front_face_order: FrontFaceOrder::CounterClockwise,
+                    color_write: (true, true, true, true),
                 },
                 display: None,
             }
         }
     }
 
+    /// Delete what dropped `Owned` handles queued
+    fn delete_dropped(&mut self) {
+        let dropped: Vec<AnyResource> = self.drop_queue.borrow_mut().drain(..).collect();
+        for resource in dropped {
+            resource.delete(self);
+        }
+    }
+
+    /// Resources created and not deleted yet, one description each
+    pub fn live_resources(&mut self) -> Vec<String> {
+        self.delete_dropped();
+
+        let mut live = vec![];
+        live.extend(self.buffers.iter().map(|b| format!("Buffer {} ({} bytes)", b.gl_buf, b.size)));
+        live.extend(
+            self.textures
+                .iter()
+                .map(|t| format!("Texture {} ({}x{})", t.texture, t.width, t.height)),
+        );
+        live.extend(self.shaders.iter().map(|s| format!("Shader {}", s.program)));
+        live.extend(self.pipelines.iter().map(|_| "Pipeline".to_string()));
+        live.extend(self.passes.iter().map(|p| format!("RenderPass {}", p.gl_fb)));
+        live
+    }
+
+    /// Called by the backends once the event handler is dropped, anything left is a leak
+    pub(crate) fn report_leaks(&mut self) {
+        let leaks = self.live_resources();
+        if !leaks.is_empty() {
+            eprintln!("winopengl: {} GPU resources were never deleted:", leaks.len());
+            for leak in leaks {
+                eprintln!("    {}", leak);
+            }
+        }
+    }
+
     pub fn features(&self) -> &Features {
         &self.features
     }
+
+    /// Draw calls, state changes, uploads and frame times of the previous frames
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Measure the GPU time of every frame with GL timer queries, see `Stats::gpu_time`.
+    /// Returns false without `Features::timer_query`.
+    pub fn enable_gpu_timer(&mut self, enabled: bool) -> bool {
+        if !enabled {
+            if let Some(timer) = self.gpu_timer.take() {
+                timer.delete();
+            }
+            self.stats.gpu_time = None;
+            return true;
+        }
+        if !self.features.timer_query {
+            return false;
+        }
+        self.gpu_timer.get_or_insert_with(GpuTimer::new);
+        true
+    }
+
+    /// Append one CSV line per frame to `path` with the frame counters and times, until `stop_trace`.
+    /// Lines are written after the buffer swap, `frame_ms` goes from the start of the frame to the end of the swap.
+    /// The first line also counts what was done before the first frame, like the event handler creation.
+    pub fn start_trace<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        self.stats.start_trace(path.as_ref())
+    }
+
+    pub fn stop_trace(&mut self) -> std::io::Result<()> {
+        self.stats.stop_trace()
+    }
+
+    /// Called by the main loop before each update with the time since the previous one
+    pub(crate) fn begin_frame(&mut self, frame_time: f32) {
+        self.stats.begin_frame(frame_time);
+    }
+
+    /// Called by the main loop after the buffer swap, closes the stats of the frame
+    pub(crate) fn end_frame(&mut self, duration: f32) {
+        let gpu_time = self.gpu_timer.as_mut().and_then(|timer| timer.frame());
+        let counters = std::mem::take(&mut self.counters);
+        self.stats.end_frame(counters, duration, gpu_time);
+    }
+
+    /// With `LoopMode::WaitEvents`, run one more update and draw after `delay` even if no event comes.
+    /// Only the earliest of the pending requests is kept.
+    pub fn schedule_update(&mut self, delay: std::time::Duration) {
+        let update = std::time::Instant::now() + delay;
+        if self.next_update.is_none_or(|next| update < next) {
+            self.next_update = Some(update);
+        }
+    }
+
+    /// Version, profile, MSAA and vsync actually obtained from the driver
+    pub fn context_info(&self) -> &ContextInfo {
+        &self.context_info
+    }
+
+    /// Size in pixels of the default framebuffer, kept up to date on resize
+    pub fn screen_size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    /// Framebuffer pixels per logical pixel, 1 unless `Conf::high_dpi` is set on a high density monitor
+    pub fn dpi_scale(&self) -> f32 {
+        self.dpi_scale
+    }
+
+    /// Called by the backends when the window size changes, the viewport of the default pass follows
+    pub(crate) fn resize(&mut self, width: i32, height: i32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Read back the currently bound framebuffer as RGBA, 4 bytes per pixel, at the size of that framebuffer:
+    /// the render pass texture inside a pass, the window otherwise.
+    /// Rows go from the top of the screen to the bottom.
+    /// Call it after drawing and before the frame is presented.
+    pub fn read_pixels(&mut self) -> Vec<u8> {
+        let (width, height) = self.pass_size.unwrap_or((self.width, self.height));
+        let row_size = 4 * width as usize;
+        let mut pixels = vec![0u8; row_size * height as usize];
+        unsafe {
+            glPixelStorei(GL_PACK_ALIGNMENT, 1);
+            glReadPixels(
+                0,
+                0,
+                width,
+                height,
+                GL_RGBA,
+                GL_UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+        }
+
+        // GL returns the bottom row first
+        let mut flipped = Vec::with_capacity(pixels.len());
+        for row in pixels.chunks_exact(row_size).rev() {
+            flipped.extend_from_slice(row);
+        }
+        flipped
+    }
 }
 
 impl Context {
     pub fn apply_pipeline(&mut self, pipeline: &Pipeline) {
         self.cache.cur_pipeline = Some(*pipeline);
+        self.counters.pipeline_changes += 1;
 
-        {
-            let pipeline = &self.pipelines[pipeline.0];
-            let shader = &mut self.shaders[pipeline.shader.0];
-            unsafe {
-                glUseProgram(shader.program);
-            }
+        let pipeline = &self.pipelines[pipeline.0];
+        let params = pipeline.params;
+        let shader = &self.shaders[pipeline.shader.0];
+        unsafe {
+            glUseProgram(shader.program);
+        }
 
-            unsafe {
-                glEnable(GL_SCISSOR_TEST);
-            }
+        unsafe {
+            glEnable(GL_SCISSOR_TEST);
+        }
 
-            unsafe {
-                glDisable(GL_DEPTH_TEST);
-            }
-            unsafe {
-                glFrontFace(GL_CCW);
-            }
+        // testing against Always without writing is the same as no depth test at all
+        if params.depth_test == Comparison::Always && !params.depth_write {
+            self.cache.set_depth_test(None);
+        } else {
+            self.cache.set_depth_test(Some(params.depth_test));
         }
+        self.cache.set_depth_write(params.depth_write);
+        self.cache.set_cull_face(params.cull_face);
+        self.cache.set_front_face_order(params.front_face_order);
+        self.cache.set_color_write(params.color_write);
+        self.cache.set_blend(
+            params
+                .color_blend
+                .map(|color| (color, params.alpha_blend.unwrap_or(color))),
+        );
     }
 
 
     pub fn apply_bindings(&mut self, bindings: &Bindings) {
+        self.counters.binding_changes += 1;
         let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
         let shader = &self.shaders[pip.shader.0];
 
@@ -401,6 +1120,7 @@ impl Context {
                 .images
                 .get(n)
                 .unwrap_or_else(|| panic!("Image count in bindings and shader did not match!"));
+            self.textures.check(bindings_image.id);
             if let Some(gl_loc) = shader_image.gl_loc {
                 unsafe {
                     self.cache.bind_texture(n, bindings_image.texture);
@@ -409,6 +1129,7 @@ impl Context {
             }
         }
 
+        self.buffers.check(bindings.index_buffer.id);
         self.cache.bind_buffer(
             GL_ELEMENT_ARRAY_BUFFER,
             bindings.index_buffer.gl_buf,
@@ -423,8 +1144,9 @@ impl Context {
 
             if let Some(Some(attribute)) = pip_attribute {
                 let vb = bindings.vertex_buffers[attribute.buffer_index];
+                self.buffers.check(vb.id);
 
-                if cached_attr.map_or(true, |cached_attr| {
+                if cached_attr.is_none_or(|cached_attr| {
                     attribute != cached_attr.attribute || cached_attr.gl_vbuf != vb.gl_buf
                 }) {
                     self.cache.bind_buffer(GL_ARRAY_BUFFER, vb.gl_buf);
@@ -434,7 +1156,7 @@ impl Context {
                             attr_index as GLuint,
                             attribute.size,
                             attribute.type_,
-                            GL_FALSE as u8,
+                            attribute.normalized as u8,
                             attribute.stride,
                             attribute.offset as *mut _,
                         );
@@ -460,46 +1182,72 @@ impl Context {
     }
 
     pub fn apply_uniforms<U>(&mut self, uniforms: &U) {
-        self.apply_uniforms_from_bytes(uniforms as *const _ as *const u8, std::mem::size_of::<U>())
+        // the reference covers size_of::<U>() bytes
+        unsafe { self.apply_uniforms_from_bytes(uniforms as *const _ as *const u8, std::mem::size_of::<U>()) }
     }
 
     #[doc(hidden)]
     /// Apply uniforms data from array of bytes with very special layout.
     /// Hidden because `apply_uniforms` is the recommended and safer way to work with uniforms.
-    pub fn apply_uniforms_from_bytes(&mut self, uniform_ptr: *const u8, size: usize) {
+    ///
+    /// # Safety
+    ///
+    /// `uniform_ptr` must point to `size` readable bytes, aligned for `f32`.
+    pub unsafe fn apply_uniforms_from_bytes(&mut self, uniform_ptr: *const u8, size: usize) {
         let pip = &self.pipelines[self.cache.cur_pipeline.unwrap().0];
         let shader = &self.shaders[pip.shader.0];
 
-        let mut offset = 0;
-
-        for (_, uniform) in shader.uniforms.iter().enumerate() {
-            assert!(
-                offset <= size - FLOAT2_SIZE / 4,
-                "Uniforms struct does not match shader uniforms layout"
-            );
+        assert_eq!(
+            size, shader.uniforms_size,
+            "Uniforms struct does not match shader uniforms layout"
+        );
+        self.counters.uniform_updates += 1;
 
+        for uniform in shader.uniforms.iter() {
             unsafe {
-                let data = (uniform_ptr as *const f32).offset(offset as isize);
-
-                if let Some(gl_loc) = uniform.gl_loc 
-                {
-                    glUniform2fv(gl_loc, uniform.array_count, data);
+                let data = uniform_ptr.add(uniform.offset);
+                let floats = data as *const f32;
+                let ints = data as *const i32;
+                let count = uniform.array_count;
+
+                if let Some(gl_loc) = uniform.gl_loc {
+                    match uniform.uniform_type {
+                        UniformType::Float1 => glUniform1fv(gl_loc, count, floats),
+                        UniformType::Float2 => glUniform2fv(gl_loc, count, floats),
+                        UniformType::Float3 => glUniform3fv(gl_loc, count, floats),
+                        UniformType::Float4 => glUniform4fv(gl_loc, count, floats),
+                        UniformType::Int1 => glUniform1iv(gl_loc, count, ints),
+                        UniformType::Int2 => glUniform2iv(gl_loc, count, ints),
+                        UniformType::Int3 => glUniform3iv(gl_loc, count, ints),
+                        UniformType::Int4 => glUniform4iv(gl_loc, count, ints),
+                        UniformType::Mat4 => glUniformMatrix4fv(gl_loc, count, 0, floats),
+                    }
                 }
             }
-            offset += FLOAT2_SIZE / 4 * uniform.array_count as usize;
         }
     }
 
-    pub fn clear(&self) {
+    /// Clears ignore the color and depth write masks of the current pipeline
+    pub fn clear(&mut self, color: Option<(f32, f32, f32, f32)>, depth: Option<f32>) {
         let mut bits = 0;
-        bits |= GL_COLOR_BUFFER_BIT;
-        unsafe {
-            glClearColor(0.0, 0.0, 0.0, 0.0)
+        if let Some((r, g, b, a)) = color {
+            bits |= GL_COLOR_BUFFER_BIT;
+            self.cache.set_color_write((true, true, true, true));
+            unsafe {
+                glClearColor(r, g, b, a);
+            }
         }
 
-        bits |= GL_DEPTH_BUFFER_BIT;
-        unsafe {
-            glClearDepthf(1.);
+        if let Some(v) = depth {
+            bits |= GL_DEPTH_BUFFER_BIT;
+            self.cache.set_depth_write(true);
+            unsafe {
+                if crate::gl::loaded::glClearDepthf() {
+                    glClearDepthf(v);
+                } else {
+                    glClearDepth(v as f64);
+                }
+            }
         }
 
         if bits != 0 {
@@ -510,21 +1258,40 @@ impl Context {
     }
 
     /// start rendering to the default frame buffer
-    pub fn begin_default_pass(&mut self) {
-        self.begin_pass();
+    pub fn begin_default_pass(&mut self, action: PassAction) {
+        self.begin_pass(None, action);
     }
 
-    /// start rendering to an offscreen framebuffer
-    pub fn begin_pass(&mut self) {
+    /// start rendering to an offscreen framebuffer, or to the default one when `pass` is None
+    pub fn begin_pass(&mut self, pass: Option<RenderPass>, action: PassAction) {
+        self.counters.passes += 1;
+        let (framebuffer, w, h) = match pass {
+            None => (self.default_framebuffer, self.width, self.height),
+            Some(pass) => {
+                let pass = &self.passes[pass.0];
+                (
+                    pass.gl_fb,
+                    pass.texture.width as i32,
+                    pass.texture.height as i32,
+                )
+            }
+        };
+        self.pass_size = pass.map(|_| (w, h));
         unsafe {
-            glBindFramebuffer(GL_FRAMEBUFFER, self.default_framebuffer);
-            glViewport(0, 0, self.width, self.height);
-            glScissor(0, 0, self.width, self.height);
+            glBindFramebuffer(GL_FRAMEBUFFER, framebuffer);
+            glViewport(0, 0, w, h);
+            glScissor(0, 0, w, h);
+        }
+        match action {
+            PassAction::Nothing => {}
+            PassAction::Clear { color, depth } => {
+                self.clear(color, depth);
+            }
         }
-        self.clear();
     }
 
     pub fn end_render_pass(&mut self) {
+        self.pass_size = None;
         unsafe {
             glBindFramebuffer(GL_FRAMEBUFFER, self.default_framebuffer);
             self.cache.bind_buffer(GL_ARRAY_BUFFER, 0);
@@ -533,11 +1300,12 @@ impl Context {
     }
 
     pub fn commit_frame(&mut self) {
+        self.delete_dropped();
         self.cache.clear_buffer_bindings();
         self.cache.clear_texture_bindings();
     }
 
-    pub fn draw(&self, base_element: i32, num_elements: i32, num_instances: i32) {
+    pub fn draw(&mut self, base_element: i32, num_elements: i32, num_instances: i32) {
         assert!(
             self.cache.cur_pipeline.is_some(),
             "Drawing without any binded pipeline"
@@ -549,14 +1317,28 @@ impl Context {
             return;
         }
 
+        let primitive_type = self.pipelines[self.cache.cur_pipeline.unwrap().0]
+            .params
+            .primitive_type;
+        self.counters.draw_calls += 1;
+
         unsafe {
-            glDrawElementsInstanced(
-                GL_TRIANGLES,
-                num_elements,
-                GL_UNSIGNED_SHORT,
-                (2_i32 * base_element) as *mut _,
-                num_instances,
-            );
+            if self.features.instancing {
+                glDrawElementsInstanced(
+                    primitive_type.into(),
+                    num_elements,
+                    GL_UNSIGNED_SHORT,
+                    (2_i32 * base_element) as *mut _,
+                    num_instances,
+                );
+            } else {
+                glDrawElements(
+                    primitive_type.into(),
+                    num_elements,
+                    GL_UNSIGNED_SHORT,
+                    (2_i32 * base_element) as *mut _,
+                );
+            }
         }
     }
 }
@@ -575,6 +1357,12 @@ fn load_shader_internal(
         glAttachShader(program, fragment_shader);
         glLinkProgram(program);
 
+        // the program keeps what it needs once linked
+        glDetachShader(program, vertex_shader);
+        glDetachShader(program, fragment_shader);
+        glDeleteShader(vertex_shader);
+        glDeleteShader(fragment_shader);
+
         let mut link_status = 0;
         glGetProgramiv(program, GL_LINK_STATUS, &mut link_status as *mut _);
         if link_status == 0 {
@@ -591,6 +1379,7 @@ fn load_shader_internal(
             assert!(max_length >= 1);
             let error_message =
                 std::string::String::from_utf8_lossy(&error_message[0..max_length as usize - 1]);
+            glDeleteProgram(program);
             return Err(ShaderError::LinkError(error_message.to_string()));
         }
 
@@ -601,30 +1390,41 @@ fn load_shader_internal(
             gl_loc: get_uniform_location(program, name),
         }).collect();
 
-        #[rustfmt::skip]
-        let uniforms = meta.uniforms.uniforms.iter().scan(0, |offset, uniform| {
-            let res = ShaderUniform {
+        let mut uniforms_size = 0;
+        let mut uniforms = vec![];
+        for uniform in meta.uniforms.uniforms.iter() {
+            uniforms.push(ShaderUniform {
                 gl_loc: get_uniform_location(program, &uniform.name),
-                _offset: *offset,
-                _size: FLOAT2_SIZE,
+                offset: uniforms_size,
+                uniform_type: uniform.uniform_type,
                 array_count: uniform.array_count as _,
-            };
-            *offset += FLOAT2_SIZE * uniform.array_count;
-            Some(res)
-        }).collect();
+            });
+            uniforms_size += uniform.uniform_type.size() * uniform.array_count;
+        }
 
         Ok(ShaderInternal {
             program,
             images,
             uniforms,
+            uniforms_size,
         })
     }
 }
 
 pub fn load_shader(shader_type: GLenum, source: &str) -> Result<GLuint, ShaderError> {
+    let shader_type_enum = match shader_type {
+        GL_VERTEX_SHADER => ShaderType::Vertex,
+        GL_FRAGMENT_SHADER => ShaderType::Fragment,
+        _ => unreachable!(),
+    };
     unsafe {
         let shader = glCreateShader(shader_type);
-        assert!(shader != 0);
+        if shader == 0 {
+            return Err(ShaderError::CompilationError {
+                shader_type: shader_type_enum,
+                error_message: "glCreateShader failed".to_string(),
+            });
+        }
 
         let cstring = CString::new(source)?;
         let csource = [cstring];
@@ -645,9 +1445,9 @@ pub fn load_shader(shader_type: GLenum, source: &str) -> Result<GLuint, ShaderEr
                 error_message.as_mut_ptr() as *mut _,
             );
 
-            assert!(max_length >= 1);
+            // some drivers give no log at all
             let mut error_message =
-                std::string::String::from_utf8_lossy(&error_message[0..max_length as usize - 1])
+                std::string::String::from_utf8_lossy(&error_message[0..(max_length.max(1) as usize - 1)])
                     .into_owned();
 
             // On Wasm + Chrome, for unknown reason, string with zero-terminator is returned. On Firefox there is no zero-terminators in JavaScript string.
@@ -655,12 +1455,9 @@ pub fn load_shader(shader_type: GLenum, source: &str) -> Result<GLuint, ShaderEr
                 error_message.pop();
             }
 
+            glDeleteShader(shader);
             return Err(ShaderError::CompilationError {
-                shader_type: match shader_type {
-                    GL_VERTEX_SHADER => ShaderType::Vertex,
-                    GL_FRAGMENT_SHADER => ShaderType::Fragment,
-                    _ => unreachable!(),
-                },
+                shader_type: shader_type_enum,
                 error_message,
             });
         }
@@ -669,21 +1466,314 @@ pub fn load_shader(shader_type: GLenum, source: &str) -> Result<GLuint, ShaderEr
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct Pipeline(usize);
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PassAction {
+    Nothing,
+    Clear {
+        color: Option<(f32, f32, f32, f32)>,
+        depth: Option<f32>,
+    },
+}
+
+impl PassAction {
+    pub fn clear_color(r: f32, g: f32, b: f32, a: f32) -> PassAction {
+        PassAction::Clear {
+            color: Some((r, g, b, a)),
+            depth: Some(1.),
+        }
+    }
+}
+
+impl Default for PassAction {
+    fn default() -> PassAction {
+        PassAction::Clear {
+            color: Some((0.0, 0.0, 0.0, 0.0)),
+            depth: Some(1.),
+        }
+    }
+}
+
+/// `glCheckFramebufferStatus` refused the attachments of a `RenderPass`
+#[derive(Clone, Debug)]
+pub struct FramebufferError {
+    pub status: GLenum,
+}
+
+impl Display for FramebufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "render pass framebuffer is incomplete, status 0x{:x}", self.status)
+    }
+}
+
+impl Error for FramebufferError {}
+
+/// Offscreen framebuffer drawing into textures, sampled afterwards like any other `Texture`.
+/// Keep in mind GL framebuffers start at the bottom left, so the result is upside down
+/// compared to a texture loaded from an image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderPass(Id);
+
+struct RenderPassInternal {
+    gl_fb: GLuint,
+    texture: Texture,
+    depth_texture: Option<Texture>,
+}
+
+impl RenderPass {
+    pub fn new(
+        ctx: &mut Context,
+        color_img: Texture,
+        depth_img: Option<Texture>,
+    ) -> Result<RenderPass, FramebufferError> {
+        ctx.textures.check(color_img.id);
+        if let Some(depth_img) = depth_img {
+            ctx.textures.check(depth_img.id);
+        }
+        let mut gl_fb = 0;
+
+        unsafe {
+            glGenFramebuffers(1, &mut gl_fb as *mut _);
+            glBindFramebuffer(GL_FRAMEBUFFER, gl_fb);
+            glFramebufferTexture2D(
+                GL_FRAMEBUFFER,
+                GL_COLOR_ATTACHMENT0,
+                GL_TEXTURE_2D,
+                color_img.texture,
+                0,
+            );
+            if let Some(depth_img) = depth_img {
+                glFramebufferTexture2D(
+                    GL_FRAMEBUFFER,
+                    GL_DEPTH_ATTACHMENT,
+                    GL_TEXTURE_2D,
+                    depth_img.texture,
+                    0,
+                );
+            }
+            let status = glCheckFramebufferStatus(GL_FRAMEBUFFER);
+            glBindFramebuffer(GL_FRAMEBUFFER, ctx.default_framebuffer);
+            if status != GL_FRAMEBUFFER_COMPLETE {
+                glDeleteFramebuffers(1, &gl_fb as *const _);
+                return Err(FramebufferError { status });
+            }
+        }
+        let pass = RenderPassInternal {
+            gl_fb,
+            texture: color_img,
+            depth_texture: depth_img,
+        };
+
+        Ok(RenderPass(ctx.passes.insert(pass)))
+    }
+
+    /// Free the framebuffer, the attached textures stay alive and have to be deleted on their own
+    pub fn delete(self, ctx: &mut Context) {
+        let pass = ctx.passes.remove(self.0);
+        unsafe {
+            glDeleteFramebuffers(1, &pass.gl_fb as *const _);
+        }
+    }
+
+    /// Color attachment, to be sampled in a later pass
+    pub fn texture(&self, ctx: &mut Context) -> Texture {
+        ctx.passes[self.0].texture
+    }
+
+    pub fn depth_texture(&self, ctx: &mut Context) -> Option<Texture> {
+        ctx.passes[self.0].depth_texture
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Never,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+    Always,
+}
+
+impl From<Comparison> for GLenum {
+    fn from(cmp: Comparison) -> Self {
+        match cmp {
+            Comparison::Never => GL_NEVER,
+            Comparison::Less => GL_LESS,
+            Comparison::LessOrEqual => GL_LEQUAL,
+            Comparison::Greater => GL_GREATER,
+            Comparison::GreaterOrEqual => GL_GEQUAL,
+            Comparison::Equal => GL_EQUAL,
+            Comparison::NotEqual => GL_NOTEQUAL,
+            Comparison::Always => GL_ALWAYS,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CullFace {
+    Nothing,
+    Front,
+    Back,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrontFaceOrder {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// How the fragment color (source) and the framebuffer color (destination) are combined
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Equation {
+    /// source + destination
+    Add,
+    /// source - destination
+    Subtract,
+    /// destination - source
+    ReverseSubtract,
+}
+
+impl From<Equation> for GLenum {
+    fn from(eq: Equation) -> Self {
+        match eq {
+            Equation::Add => GL_FUNC_ADD,
+            Equation::Subtract => GL_FUNC_SUBTRACT,
+            Equation::ReverseSubtract => GL_FUNC_REVERSE_SUBTRACT,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendValue {
+    SourceColor,
+    SourceAlpha,
+    DestinationColor,
+    DestinationAlpha,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    Value(BlendValue),
+    OneMinusValue(BlendValue),
+    SourceAlphaSaturate,
+}
+
+impl From<BlendFactor> for GLenum {
+    fn from(factor: BlendFactor) -> Self {
+        match factor {
+            BlendFactor::Zero => GL_ZERO,
+            BlendFactor::One => GL_ONE,
+            BlendFactor::Value(BlendValue::SourceColor) => GL_SRC_COLOR,
+            BlendFactor::Value(BlendValue::SourceAlpha) => GL_SRC_ALPHA,
+            BlendFactor::Value(BlendValue::DestinationColor) => GL_DST_COLOR,
+            BlendFactor::Value(BlendValue::DestinationAlpha) => GL_DST_ALPHA,
+            BlendFactor::OneMinusValue(BlendValue::SourceColor) => GL_ONE_MINUS_SRC_COLOR,
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha) => GL_ONE_MINUS_SRC_ALPHA,
+            BlendFactor::OneMinusValue(BlendValue::DestinationColor) => GL_ONE_MINUS_DST_COLOR,
+            BlendFactor::OneMinusValue(BlendValue::DestinationAlpha) => GL_ONE_MINUS_DST_ALPHA,
+            BlendFactor::SourceAlphaSaturate => GL_SRC_ALPHA_SATURATE,
+        }
+    }
+}
+
+/// `equation(sfactor * source, dfactor * destination)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlendState {
+    equation: Equation,
+    sfactor: BlendFactor,
+    dfactor: BlendFactor,
+}
+
+impl BlendState {
+    pub fn new(equation: Equation, sfactor: BlendFactor, dfactor: BlendFactor) -> BlendState {
+        BlendState {
+            equation,
+            sfactor,
+            dfactor,
+        }
+    }
+
+    /// Usual transparency: source over destination by source alpha
+    pub fn alpha() -> BlendState {
+        BlendState::new(
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        )
+    }
+}
+
+/// Write (red, green, blue, alpha)
+pub type ColorMask = (bool, bool, bool, bool);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrimitiveType {
+    Triangles,
+    Lines,
+    Points,
+}
+
+impl From<PrimitiveType> for GLenum {
+    fn from(primitive_type: PrimitiveType) -> Self {
+        match primitive_type {
+            PrimitiveType::Triangles => GL_TRIANGLES,
+            PrimitiveType::Lines => GL_LINES,
+            PrimitiveType::Points => GL_POINTS,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PipelineParams {
+    pub cull_face: CullFace,
+    pub front_face_order: FrontFaceOrder,
+    pub depth_test: Comparison,
+    pub depth_write: bool,
+    /// `None` disables blending, the fragment color replaces the destination
+    pub color_blend: Option<BlendState>,
+    /// Separate blending for the alpha channel, `None` blends it like the color
+    pub alpha_blend: Option<BlendState>,
+    pub color_write: ColorMask,
+    pub primitive_type: PrimitiveType,
+}
+
+impl Default for PipelineParams {
+    fn default() -> PipelineParams {
+        PipelineParams {
+            cull_face: CullFace::Nothing,
+            front_face_order: FrontFaceOrder::CounterClockwise,
+            depth_test: Comparison::Always,
+            depth_write: false,
+            color_blend: None,
+            alpha_blend: None,
+            color_write: (true, true, true, true),
+            primitive_type: PrimitiveType::Triangles,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pipeline(Id);
 impl Pipeline {
     pub fn new(
         ctx: &mut Context,
+        buffer_layout: &[BufferLayout],
         attributes: &[VertexAttribute],
         shader: Shader,
     ) -> Pipeline {
-        Self::with_params(ctx, attributes, shader)
+        Self::with_params(ctx, buffer_layout, attributes, shader, Default::default())
     }
 
     pub fn with_params(
         ctx: &mut Context,
+        buffer_layout: &[BufferLayout],
         attributes: &[VertexAttribute],
         shader: Shader,
+        params: PipelineParams,
     ) -> Pipeline {
         #[derive(Clone, Copy, Default)]
         struct BufferCacheData {
@@ -692,47 +1782,64 @@ impl Pipeline {
         }
 
         let mut buffer_cache: Vec<BufferCacheData> =
-            vec![BufferCacheData::default(); 1];
+            vec![BufferCacheData::default(); buffer_layout.len()];
 
         for VertexAttribute {
+            format,
             buffer_index,
             ..
         } in attributes
         {
-            let mut cache = buffer_cache
-                .get_mut(*buffer_index)
-                .unwrap_or_else(|| panic!());
+            let layout = buffer_layout.get(*buffer_index).unwrap_or_else(|| {
+                panic!("attribute buffer_index {} has no BufferLayout", buffer_index)
+            });
+            let cache = &mut buffer_cache[*buffer_index];
 
-            cache.stride += 8;
-            // WebGL 1 limitation
-            assert!(cache.stride <= 255);
+            if layout.stride == 0 {
+                cache.stride += format.size_bytes();
+            } else {
+                cache.stride = layout.stride;
+            }
+            if let Some(max) = ctx.features.max_vertex_attrib_stride {
+                assert!(
+                    cache.stride <= max,
+                    "vertex stride {} is above GL_MAX_VERTEX_ATTRIB_STRIDE {}",
+                    cache.stride,
+                    max
+                );
+            }
         }
 
         let program = ctx.shaders[shader.0].program;
 
-        let mut vertex_layout: Vec<Option<VertexAttributeInternal>> = vec![None; 2];
+        let mut vertex_layout: Vec<Option<VertexAttributeInternal>> =
+            vec![None; MAX_VERTEX_ATTRIBUTES];
 
         for VertexAttribute {
             name,
+            format,
             buffer_index,
         } in attributes
         {
-            let mut buffer_data = &mut buffer_cache
-                .get_mut(*buffer_index)
-                .unwrap_or_else(|| panic!());
+            let layout = buffer_layout[*buffer_index];
+            let buffer_data = &mut buffer_cache[*buffer_index];
             let cname = CString::new(*name).unwrap_or_else(|e| panic!("{}", e));
             let attr_loc = unsafe { glGetAttribLocation(program, cname.as_ptr() as *const _) };
             let attr_loc = if attr_loc == -1 { None } else { Some(attr_loc) };
-            let divisor = 0;
+            let divisor = match layout.step_func {
+                VertexStep::PerVertex => 0,
+                VertexStep::PerInstance => layout.step_rate,
+            };
 
-            for i in 0..1 {
+            for i in 0..format.locations() {
                 if let Some(attr_loc) = attr_loc {
                     let attr_loc = attr_loc as GLuint + i as GLuint;
 
                     let attr = VertexAttributeInternal {
                         attr_loc,
-                        size: 2,
-                        type_: GL_FLOAT,
+                        size: format.components(),
+                        type_: format.type_(),
+                        normalized: format.normalized(),
                         offset: buffer_data.offset,
                         stride: buffer_data.stride,
                         buffer_index: *buffer_index,
@@ -747,17 +1854,25 @@ impl Pipeline {
                     );
                     vertex_layout[attr_loc as usize] = Some(attr);
                 }
-                buffer_data.offset += 8_i64
+                buffer_data.offset += (format.components() * format.component_size()) as i64
             }
         }
 
         let pipeline = PipelineInternal {
             layout: vertex_layout,
             shader,
+            params,
         };
 
-        ctx.pipelines.push(pipeline);
-        Pipeline(ctx.pipelines.len() - 1)
+        Pipeline(ctx.pipelines.insert(pipeline))
+    }
+
+    /// Forget the pipeline, its shader is not deleted
+    pub fn delete(self, ctx: &mut Context) {
+        ctx.pipelines.remove(self.0);
+        if ctx.cache.cur_pipeline == Some(self) {
+            ctx.cache.cur_pipeline = None;
+        }
     }
 }
 
@@ -766,6 +1881,7 @@ struct VertexAttributeInternal {
     attr_loc: GLuint,
     size: i32,
     type_: GLuint,
+    normalized: bool,
     offset: i64,
     stride: i32,
     buffer_index: usize,
@@ -775,6 +1891,7 @@ struct VertexAttributeInternal {
 struct PipelineInternal {
     layout: Vec<Option<VertexAttributeInternal>>,
     shader: Shader,
+    params: PipelineParams,
 }
 
 /// Geometry bindings
@@ -791,32 +1908,124 @@ pub enum BufferType {
     IndexBuffer,
 }
 
+struct BufferInternal {
+    gl_buf: GLuint,
+    size: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Usage {
+    /// Uploaded once at creation
+    Immutable,
+    /// Re-uploaded often, up to every frame, with `Buffer::update`
+    Stream,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Buffer {
+    id: Id,
     gl_buf: GLuint,
+    buffer_type: BufferType,
+    usage: Usage,
+    size: usize,
 }
+
 impl Buffer {
     pub fn immutable<T>(ctx: &mut Context, buffer_type: BufferType, data: &[T]) -> Buffer {
+        let size = mem::size_of_val(data);
+        let buffer = Self::new_internal(ctx, buffer_type, Usage::Immutable, size);
+        buffer.upload(ctx, data);
+        buffer
+    }
+
+    /// Buffer of `size` bytes, filled later with `update`
+    pub fn stream(ctx: &mut Context, buffer_type: BufferType, size: usize) -> Buffer {
+        Self::new_internal(ctx, buffer_type, Usage::Stream, size)
+    }
+
+    /// Replace the content of a stream buffer, `data` may be smaller than the allocation.
+    /// The previous storage is orphaned so a frame still using it does not stall the upload.
+    pub fn update<T>(&self, ctx: &mut Context, data: &[T]) {
+        assert!(
+            self.usage == Usage::Stream,
+            "Immutable buffers can't be updated"
+        );
+        assert!(
+            mem::size_of_val(data) <= self.size,
+            "Buffer update of {} bytes is bigger than the {} bytes allocated",
+            mem::size_of_val(data),
+            self.size
+        );
+        ctx.buffers.check(self.id);
+        self.upload(ctx, data);
+    }
+
+    pub fn delete(self, ctx: &mut Context) {
+        ctx.buffers.remove(self.id);
+        ctx.cache.forget_buffer(self.gl_buf);
+        unsafe {
+            glDeleteBuffers(1, &self.gl_buf as *const _);
+        }
+    }
+
+    /// Size in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
 
-        let gl_target = match buffer_type {
+    fn gl_target(&self) -> GLenum {
+        match self.buffer_type {
             BufferType::VertexBuffer => GL_ARRAY_BUFFER,
             BufferType::IndexBuffer => GL_ELEMENT_ARRAY_BUFFER,
-        };
-        let gl_usage = GL_STATIC_DRAW;
-        let size = mem::size_of_val(data);
+        }
+    }
+
+    fn gl_usage(&self) -> GLenum {
+        match self.usage {
+            Usage::Immutable => GL_STATIC_DRAW,
+            Usage::Stream => GL_STREAM_DRAW,
+        }
+    }
+
+    fn new_internal(ctx: &mut Context, buffer_type: BufferType, usage: Usage, size: usize) -> Buffer {
         let mut gl_buf: u32 = 0;
 
         unsafe {
             glGenBuffers(1, &mut gl_buf as *mut _);
+        }
+
+        let buffer = Buffer {
+            id: ctx.buffers.insert(BufferInternal { gl_buf, size }),
+            gl_buf,
+            buffer_type,
+            usage,
+            size,
+        };
+        let gl_target = buffer.gl_target();
+
+        unsafe {
             ctx.cache.store_buffer_binding(gl_target);
             ctx.cache.bind_buffer(gl_target, gl_buf);
-            glBufferData(gl_target, size as _, std::ptr::null() as *const _, gl_usage);
-            glBufferSubData(gl_target, 0, size as _, data.as_ptr() as *const _);
+            glBufferData(gl_target, size as _, std::ptr::null() as *const _, buffer.gl_usage());
             ctx.cache.restore_buffer_binding(gl_target);
         }
 
-        Buffer {
-            gl_buf,
+        buffer
+    }
+
+    fn upload<T>(&self, ctx: &mut Context, data: &[T]) {
+        let gl_target = self.gl_target();
+
+        unsafe {
+            ctx.cache.store_buffer_binding(gl_target);
+            ctx.cache.bind_buffer(gl_target, self.gl_buf);
+            if self.usage == Usage::Stream {
+                glBufferData(gl_target, self.size as _, std::ptr::null() as *const _, self.gl_usage());
+            }
+            glBufferSubData(gl_target, 0, mem::size_of_val(data) as _, data.as_ptr() as *const _);
+            ctx.cache.restore_buffer_binding(gl_target);
         }
+        ctx.counters.buffer_uploads += 1;
+        ctx.counters.uploaded_bytes += mem::size_of_val(data) as u64;
     }
-}
\ No newline at end of file
+}
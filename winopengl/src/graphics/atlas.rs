@@ -0,0 +1,218 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::graphics::{Context, Texture, TextureParams};
+
+/// Part of a texture drawn as one quad, see `SpriteBatch::add_sprite`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub texture: Texture,
+    /// (u, v, width, height) of the region, normalized, v going down from the top row
+    pub uv: [f32; 4],
+    /// Size in pixels
+    pub size: (f32, f32),
+}
+
+impl From<Texture> for Sprite {
+    fn from(texture: Texture) -> Sprite {
+        Sprite {
+            texture,
+            uv: [0., 0., 1., 1.],
+            size: (texture.width as f32, texture.height as f32),
+        }
+    }
+}
+
+struct Image<K> {
+    key: K,
+    width: u32,
+    height: u32,
+    bytes: Vec<u8>,
+}
+
+/// Packs RGBA8 images into a single texture.
+///
+/// Images are placed on shelves, tallest first, in the smallest power of two
+/// square-ish texture they fit in. Each image is surrounded by `padding` pixels
+/// repeating its border so linear filtering never samples a neighbour.
+pub struct AtlasBuilder<K> {
+    images: Vec<Image<K>>,
+    padding: u32,
+}
+
+impl<K: Eq + Hash + Clone> Default for AtlasBuilder<K> {
+    fn default() -> Self {
+        AtlasBuilder::new()
+    }
+}
+
+impl<K: Eq + Hash + Clone> AtlasBuilder<K> {
+    pub fn new() -> AtlasBuilder<K> {
+        AtlasBuilder {
+            images: vec![],
+            padding: 1,
+        }
+    }
+
+    pub fn padding(mut self, padding: u32) -> AtlasBuilder<K> {
+        self.padding = padding;
+        self
+    }
+
+    /// `bytes` are tightly packed RGBA8 rows, top row first, like `Texture::new`
+    pub fn add(&mut self, key: K, width: u32, height: u32, bytes: &[u8]) {
+        assert_eq!(
+            (width * height * 4) as usize,
+            bytes.len(),
+            "Atlas image data size does not match {}x{} RGBA8",
+            width,
+            height
+        );
+        self.images.push(Image {
+            key,
+            width,
+            height,
+            bytes: bytes.to_vec(),
+        });
+    }
+
+    /// Shelf packing in a `width` wide texture, returns the top left of every image and the used height
+    fn pack(&self, width: u32) -> Option<(Vec<(u32, u32)>, u32)> {
+        let pad = self.padding;
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.images[i].height));
+
+        let mut positions = vec![(0, 0); self.images.len()];
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for i in order {
+            let image = &self.images[i];
+            let (w, h) = (image.width + 2 * pad, image.height + 2 * pad);
+            if w > width {
+                return None;
+            }
+            if x + w > width {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            positions[i] = (x + pad, y + pad);
+            x += w;
+            shelf_height = shelf_height.max(h);
+        }
+        Some((positions, y + shelf_height))
+    }
+
+    pub fn build(&self, ctx: &mut Context) -> TextureAtlas<K> {
+        let area: u32 = self
+            .images
+            .iter()
+            .map(|i| (i.width + 2 * self.padding) * (i.height + 2 * self.padding))
+            .sum();
+        let mut width = ((area as f32).sqrt() as u32).max(1).next_power_of_two();
+        let (positions, used_height) = loop {
+            match self.pack(width) {
+                Some((positions, height)) if height <= width => break (positions, height),
+                _ => width *= 2,
+            }
+        };
+        let height = used_height.max(1).next_power_of_two();
+
+        let mut bytes = vec![0u8; (width * height * 4) as usize];
+        for (image, &(x, y)) in self.images.iter().zip(&positions) {
+            let pad = self.padding as i64;
+            for ty in -pad..image.height as i64 + pad {
+                for tx in -pad..image.width as i64 + pad {
+                    // padding repeats the closest border pixel
+                    let sx = tx.clamp(0, image.width as i64 - 1) as u32;
+                    let sy = ty.clamp(0, image.height as i64 - 1) as u32;
+                    let src = ((sy * image.width + sx) * 4) as usize;
+                    let dst = (((y as i64 + ty) as u32 * width + (x as i64 + tx) as u32) * 4) as usize;
+                    bytes[dst..dst + 4].copy_from_slice(&image.bytes[src..src + 4]);
+                }
+            }
+        }
+
+        let texture = Texture::new(
+            ctx,
+            &bytes,
+            TextureParams {
+                width,
+                height,
+                ..Default::default()
+            },
+        );
+        let sprites = self
+            .images
+            .iter()
+            .zip(&positions)
+            .map(|(image, &(x, y))| {
+                let sprite = Sprite {
+                    texture,
+                    uv: [
+                        x as f32 / width as f32,
+                        y as f32 / height as f32,
+                        image.width as f32 / width as f32,
+                        image.height as f32 / height as f32,
+                    ],
+                    size: (image.width as f32, image.height as f32),
+                };
+                (image.key.clone(), sprite)
+            })
+            .collect();
+
+        TextureAtlas { texture, sprites }
+    }
+}
+
+/// Texture built by `AtlasBuilder` and where each image ended up in it
+pub struct TextureAtlas<K> {
+    pub texture: Texture,
+    sprites: HashMap<K, Sprite>,
+}
+
+impl<K: Eq + Hash> TextureAtlas<K> {
+    pub fn sprite(&self, key: &K) -> Sprite {
+        *self.sprites.get(key).expect("No such image in the atlas")
+    }
+
+    /// Free the atlas texture, sprites taken from it must not be drawn anymore
+    pub fn delete(self, ctx: &mut Context) {
+        self.texture.delete(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_keeps_padded_images_apart() {
+        let sizes = [(16, 16), (5, 30), (30, 5), (7, 7), (1, 1), (20, 12), (3, 9), (12, 3)];
+        let mut builder = AtlasBuilder::new().padding(2);
+        for (i, &(w, h)) in sizes.iter().enumerate() {
+            builder.add(i, w, h, &vec![0; (w * h * 4) as usize]);
+        }
+        let (positions, height) = builder.pack(48).unwrap();
+
+        // rectangles with their padding around them
+        let rects: Vec<(u32, u32, u32, u32)> = positions
+            .iter()
+            .zip(&sizes)
+            .map(|(&(x, y), &(w, h))| (x - 2, y - 2, x + w + 2, y + h + 2))
+            .collect();
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.2 <= 48 && a.3 <= height, "image {} outside of the texture", i);
+            for (j, b) in rects.iter().enumerate().skip(i + 1) {
+                let apart = a.2 <= b.0 || b.2 <= a.0 || a.3 <= b.1 || b.3 <= a.1;
+                assert!(apart, "images {} and {} overlap", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn pack_refuses_images_wider_than_the_texture() {
+        let mut builder = AtlasBuilder::new().padding(1);
+        builder.add("wide", 15, 1, &[0; 60]);
+        assert!(builder.pack(16).is_none());
+        assert!(builder.pack(17).is_some());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::graphics::{ortho, Mat4};
+
+/// Orthographic camera over a 2D world with y going up.
+///
+/// `zoom` is the number of screen pixels one world unit covers, so a game
+/// can work in grid cells and still draw pixel sized sprites at zoom 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera2D {
+    /// World point shown at the center of the viewport
+    pub target: (f32, f32),
+    pub zoom: f32,
+    /// Size in pixels of the render target the camera draws into
+    pub viewport: (f32, f32),
+}
+
+impl Camera2D {
+    pub fn new(viewport: (f32, f32), zoom: f32) -> Camera2D {
+        Camera2D {
+            target: (0., 0.),
+            zoom,
+            viewport,
+        }
+    }
+
+    /// Size of the visible world rectangle, in world units
+    pub fn view_size(&self) -> (f32, f32) {
+        (self.viewport.0 / self.zoom, self.viewport.1 / self.zoom)
+    }
+
+    pub fn projection(&self) -> Mat4 {
+        let (w, h) = self.view_size();
+        let (x, y) = self.target;
+        ortho(x - w / 2., x + w / 2., y - h / 2., y + h / 2.)
+    }
+
+    /// Move `target` so that the world rectangle `(0, 0)..size` fills as much of the view as it can,
+    /// a side smaller than the view is centered instead
+    pub fn clamp_to(&mut self, size: (f32, f32)) {
+        let (w, h) = self.view_size();
+        let clamp = |target: f32, view: f32, size: f32| {
+            if size <= view {
+                size / 2.
+            } else {
+                target.clamp(view / 2., size - view / 2.)
+            }
+        };
+        self.target = (
+            clamp(self.target.0, w, size.0),
+            clamp(self.target.1, h, size.1),
+        );
+    }
+
+    /// Window pixel, origin at the top left and y going down, to world coordinates
+    pub fn screen_to_world(&self, position: (f32, f32)) -> (f32, f32) {
+        (
+            self.target.0 + (position.0 - self.viewport.0 / 2.) / self.zoom,
+            self.target.1 - (position.1 - self.viewport.1 / 2.) / self.zoom,
+        )
+    }
+
+    /// Inverse of `screen_to_world`
+    pub fn world_to_screen(&self, position: (f32, f32)) -> (f32, f32) {
+        (
+            (position.0 - self.target.0) * self.zoom + self.viewport.0 / 2.,
+            (self.target.1 - position.1) * self.zoom + self.viewport.1 / 2.,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_and_world_conversions_undo_each_other() {
+        let mut camera = Camera2D::new((800., 600.), 32.);
+        camera.target = (12.5, 7.);
+        for point in [(0., 0.), (800., 600.), (123.5, 456.25)] {
+            let back = camera.world_to_screen(camera.screen_to_world(point));
+            assert!((back.0 - point.0).abs() < 1e-3 && (back.1 - point.1).abs() < 1e-3);
+        }
+        let world = (3., -4.);
+        let back = camera.screen_to_world(camera.world_to_screen(world));
+        assert!((back.0 - world.0).abs() < 1e-4 && (back.1 - world.1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn screen_y_goes_down_from_the_top_left() {
+        let mut camera = Camera2D::new((800., 600.), 32.);
+        camera.target = (12.5, 7.);
+        assert_eq!(camera.screen_to_world((400., 300.)), (12.5, 7.));
+        assert_eq!(camera.screen_to_world((0., 0.)), (0., 16.375));
+    }
+}
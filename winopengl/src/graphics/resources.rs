@@ -0,0 +1,205 @@
+use std::{cell::RefCell, ops::Deref, rc::Rc};
+
+use crate::graphics::{Buffer, Context, Pipeline, RenderPass, Shader, Texture};
+
+/// Generational handle: the slot index plus the generation the slot had when the resource was created.
+/// Once the resource is deleted the slot generation moves on and every old copy of the handle
+/// is detected as stale instead of silently pointing to whatever reused the slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Id {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Storage behind the resource handles, freed slots are reused with a bumped generation.
+pub(crate) struct Slots<T> {
+    kind: &'static str,
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+}
+
+impl<T> Slots<T> {
+    pub(crate) fn new(kind: &'static str) -> Slots<T> {
+        Slots {
+            kind,
+            slots: vec![],
+            free: vec![],
+        }
+    }
+
+    pub(crate) fn insert(&mut self, value: T) -> Id {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some(value);
+                Id {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                Id {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, id: Id) -> Option<&T> {
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    pub(crate) fn remove(&mut self, id: Id) -> T {
+        let kind = self.kind;
+        let value = self
+            .slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| {
+                slot.generation = slot.generation.wrapping_add(1);
+                slot.value.take()
+            })
+            .unwrap_or_else(|| panic!("{} {:?} deleted twice", kind, id));
+        self.free.push(id.index);
+        value
+    }
+
+    /// Panics when `id` was deleted
+    pub(crate) fn check(&self, id: Id) {
+        let _ = &self[id];
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+}
+
+impl<T> std::ops::Index<Id> for Slots<T> {
+    type Output = T;
+
+    fn index(&self, id: Id) -> &T {
+        self.get(id)
+            .unwrap_or_else(|| panic!("{} {:?} used after being deleted", self.kind, id))
+    }
+}
+
+/// Any handle, as queued by a dropped `Owned`
+#[derive(Clone, Copy, Debug)]
+pub enum AnyResource {
+    Buffer(Buffer),
+    Texture(Texture),
+    Shader(Shader),
+    Pipeline(Pipeline),
+    RenderPass(RenderPass),
+}
+
+pub trait Resource: Copy {
+    fn delete(self, ctx: &mut Context);
+    fn into_any(self) -> AnyResource;
+}
+
+impl Resource for Buffer {
+    fn delete(self, ctx: &mut Context) {
+        Buffer::delete(self, ctx)
+    }
+    fn into_any(self) -> AnyResource {
+        AnyResource::Buffer(self)
+    }
+}
+
+impl Resource for Texture {
+    fn delete(self, ctx: &mut Context) {
+        Texture::delete(self, ctx)
+    }
+    fn into_any(self) -> AnyResource {
+        AnyResource::Texture(self)
+    }
+}
+
+impl Resource for Shader {
+    fn delete(self, ctx: &mut Context) {
+        Shader::delete(self, ctx)
+    }
+    fn into_any(self) -> AnyResource {
+        AnyResource::Shader(self)
+    }
+}
+
+impl Resource for Pipeline {
+    fn delete(self, ctx: &mut Context) {
+        Pipeline::delete(self, ctx)
+    }
+    fn into_any(self) -> AnyResource {
+        AnyResource::Pipeline(self)
+    }
+}
+
+impl Resource for RenderPass {
+    fn delete(self, ctx: &mut Context) {
+        RenderPass::delete(self, ctx)
+    }
+    fn into_any(self) -> AnyResource {
+        AnyResource::RenderPass(self)
+    }
+}
+
+impl Resource for AnyResource {
+    fn delete(self, ctx: &mut Context) {
+        match self {
+            AnyResource::Buffer(r) => r.delete(ctx),
+            AnyResource::Texture(r) => r.delete(ctx),
+            AnyResource::Shader(r) => r.delete(ctx),
+            AnyResource::Pipeline(r) => r.delete(ctx),
+            AnyResource::RenderPass(r) => r.delete(ctx),
+        }
+    }
+    fn into_any(self) -> AnyResource {
+        self
+    }
+}
+
+pub(crate) type DropQueue = Rc<RefCell<Vec<AnyResource>>>;
+
+/// Handle deleted when dropped.
+/// Dropping has no access to the context, so the resource is queued
+/// and actually deleted on the next `commit_frame`.
+pub struct Owned<T: Resource> {
+    resource: T,
+    queue: DropQueue,
+}
+
+impl<T: Resource> Owned<T> {
+    pub fn new(ctx: &mut Context, resource: T) -> Owned<T> {
+        Owned {
+            resource,
+            queue: ctx.drop_queue.clone(),
+        }
+    }
+}
+
+impl<T: Resource> Deref for Owned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<T: Resource> Drop for Owned<T> {
+    fn drop(&mut self) {
+        self.queue.borrow_mut().push(self.resource.into_any());
+    }
+}
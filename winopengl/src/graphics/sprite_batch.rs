@@ -0,0 +1,278 @@
+use crate::graphics::{
+    BlendState, Bindings, Buffer, BufferLayout, BufferType, Context, Owned, Pipeline,
+    Mat4, PipelineParams, Shader, ShaderError, ShaderMeta, Sprite, Texture, Transform2D, UniformBlockLayout, UniformDesc, UniformType,
+    VertexAttribute, VertexFormat, VertexStep,
+};
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec2 corner;
+attribute vec2 uv;
+attribute vec2 inst_pos;
+attribute vec2 inst_size;
+attribute float inst_rotation;
+attribute vec4 inst_tint;
+attribute vec4 inst_uv;
+
+uniform mat4 projection;
+
+// lowp cannot address texels past 256, atlas coordinates need more
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+varying highp vec2 texcoord;
+#else
+varying mediump vec2 texcoord;
+#endif
+varying lowp vec4 tint;
+
+void main() {
+    vec2 p = corner * inst_size;
+    float c = cos(inst_rotation);
+    float s = sin(inst_rotation);
+    p = vec2(c * p.x - s * p.y, s * p.x + c * p.y) + inst_pos;
+    gl_Position = projection * vec4(p, 0.0, 1.0);
+    texcoord = inst_uv.xy + uv * inst_uv.zw;
+    tint = inst_tint;
+}"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 100
+// lowp cannot address texels past 256, atlas coordinates need more
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+varying highp vec2 texcoord;
+#else
+varying mediump vec2 texcoord;
+#endif
+varying lowp vec4 tint;
+
+uniform sampler2D tex;
+
+void main() {
+    gl_FragColor = texture2D(tex, texcoord) * tint;
+}"#;
+
+/// Unit quad centered on 0 as (x, y, u, v), with the top of the texture up
+const CORNERS: [[f32; 4]; 4] = [
+    [-0.5, -0.5, 0., 1.],
+    [0.5, -0.5, 1., 1.],
+    [0.5, 0.5, 1., 0.],
+    [-0.5, 0.5, 0., 0.],
+];
+
+/// u16 indices limit the non instanced fallback
+const MAX_FALLBACK_SPRITES: usize = 0x10000 / 4;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct Instance {
+    position: [f32; 2],
+    size: [f32; 2],
+    rotation: f32,
+    tint: [u8; 4],
+    uv: [f32; 4],
+}
+
+struct Buffers {
+    vertex: Owned<Buffer>,
+    instances: Owned<Buffer>,
+    index: Owned<Buffer>,
+    capacity: usize,
+}
+
+/// Draws many textured quads with one draw call per run of sprites sharing a texture.
+///
+/// Sprites are placed by a `Transform2D` of their center, in the units of the projection
+/// given to `draw`, one unit being one pixel of the sprite.
+/// With `Features::instancing` each sprite is one instance of a single quad,
+/// otherwise the quads are expanded on the CPU and drawn as plain triangles.
+pub struct SpriteBatch {
+    pipeline: Owned<Pipeline>,
+    _shader: Owned<Shader>,
+    buffers: Buffers,
+    instancing: bool,
+    instances: Vec<Instance>,
+    /// Consecutive sprites using the same texture
+    runs: Vec<(Texture, usize)>,
+    /// `projection` then the values of the extra uniforms
+    uniforms: Vec<f32>,
+}
+
+impl SpriteBatch {
+    pub fn new(ctx: &mut Context) -> Result<SpriteBatch, ShaderError> {
+        Self::with_fragment_shader(ctx, FRAGMENT_SHADER, vec![])
+    }
+
+    /// Batch drawn with another fragment shader, which gets the `texcoord` and `tint` varyings
+    /// and the `tex` sampler. `uniforms` are declared after `projection`, they must be made of
+    /// floats only and start at zero, see `set_uniforms`.
+    pub fn with_fragment_shader(
+        ctx: &mut Context,
+        fragment_shader: &str,
+        uniforms: Vec<UniformDesc>,
+    ) -> Result<SpriteBatch, ShaderError> {
+        let instancing = ctx.features().instancing;
+        let extra_floats: usize = uniforms.iter().map(|u| u.uniform_type.size() * u.array_count / 4).sum();
+        let shader = Shader::new(
+            ctx,
+            VERTEX_SHADER,
+            fragment_shader,
+            ShaderMeta {
+                images: vec!["tex".to_string()],
+                uniforms: UniformBlockLayout {
+                    uniforms: std::iter::once(UniformDesc::new("projection", UniformType::Mat4))
+                        .chain(uniforms)
+                        .collect(),
+                },
+            },
+        )?;
+
+        let instance_step = if instancing {
+            VertexStep::PerInstance
+        } else {
+            VertexStep::PerVertex
+        };
+        let pipeline = Pipeline::with_params(
+            ctx,
+            &[
+                BufferLayout::default(),
+                BufferLayout {
+                    step_func: instance_step,
+                    ..Default::default()
+                },
+            ],
+            &[
+                VertexAttribute::new("corner", VertexFormat::Float2),
+                VertexAttribute::new("uv", VertexFormat::Float2),
+                VertexAttribute::with_buffer("inst_pos", VertexFormat::Float2, 1),
+                VertexAttribute::with_buffer("inst_size", VertexFormat::Float2, 1),
+                VertexAttribute::with_buffer("inst_rotation", VertexFormat::Float1, 1),
+                VertexAttribute::with_buffer("inst_tint", VertexFormat::Byte4, 1),
+                VertexAttribute::with_buffer("inst_uv", VertexFormat::Float4, 1),
+            ],
+            shader,
+            PipelineParams {
+                color_blend: Some(BlendState::alpha()),
+                ..Default::default()
+            },
+        );
+
+        Ok(SpriteBatch {
+            pipeline: Owned::new(ctx, pipeline),
+            _shader: Owned::new(ctx, shader),
+            buffers: Self::new_buffers(ctx, instancing, 64),
+            instancing,
+            instances: vec![],
+            runs: vec![],
+            uniforms: vec![0.; 16 + extra_floats],
+        })
+    }
+
+    /// Values of the uniforms given to `with_fragment_shader`, in their order, for the next draws
+    pub fn set_uniforms(&mut self, values: &[f32]) {
+        self.uniforms[16..].copy_from_slice(values);
+    }
+
+    fn new_buffers(ctx: &mut Context, instancing: bool, capacity: usize) -> Buffers {
+        // the fallback repeats the quad and every instance once per corner
+        let quads = if instancing { 1 } else { capacity };
+        let per_instance = if instancing { 1 } else { 4 };
+
+        let vertices: Vec<[f32; 4]> =
+            (0..quads).flat_map(|_| CORNERS.iter().copied()).collect();
+        let indices: Vec<u16> = (0..quads as u16)
+            .flat_map(|q| [0, 1, 2, 0, 2, 3].iter().map(move |i| q * 4 + i))
+            .collect();
+        let vertex = Buffer::immutable(ctx, BufferType::VertexBuffer, &vertices);
+        let index = Buffer::immutable(ctx, BufferType::IndexBuffer, &indices);
+        let instances = Buffer::stream(
+            ctx,
+            BufferType::VertexBuffer,
+            capacity * per_instance * std::mem::size_of::<Instance>(),
+        );
+
+        Buffers {
+            vertex: Owned::new(ctx, vertex),
+            instances: Owned::new(ctx, instances),
+            index: Owned::new(ctx, index),
+            capacity,
+        }
+    }
+
+    /// Queue the whole `texture`, centered on the `transform` translation and multiplied by `tint`.
+    pub fn add(&mut self, texture: Texture, transform: Transform2D, tint: (u8, u8, u8, u8)) {
+        self.add_sprite(texture.into(), transform, tint);
+    }
+
+    /// Same as `add` for a region of a texture, sprites from the same atlas share a draw call
+    pub fn add_sprite(&mut self, sprite: Sprite, transform: Transform2D, tint: (u8, u8, u8, u8)) {
+        self.instances.push(Instance {
+            position: [transform.translation.0, transform.translation.1],
+            // a negative scale flips the quad, uvs included
+            size: [
+                sprite.size.0 * transform.scale.0,
+                sprite.size.1 * transform.scale.1,
+            ],
+            rotation: transform.rotation,
+            tint: [tint.0, tint.1, tint.2, tint.3],
+            uv: sprite.uv,
+        });
+        match self.runs.last_mut() {
+            Some((last, count)) if *last == sprite.texture => *count += 1,
+            _ => self.runs.push((sprite.texture, 1)),
+        }
+    }
+
+    /// Number of draw calls the next `draw` will issue
+    pub fn draw_calls(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Draw everything queued since the last call, `projection` mapping sprite units to clip space
+    pub fn draw(&mut self, ctx: &mut Context, projection: &Mat4) {
+        let largest = self.runs.iter().map(|(_, count)| *count).max().unwrap_or(0);
+        if largest > self.buffers.capacity {
+            let capacity = largest.next_power_of_two();
+            if !self.instancing {
+                assert!(
+                    capacity <= MAX_FALLBACK_SPRITES,
+                    "More than {} sprites with the same texture",
+                    MAX_FALLBACK_SPRITES
+                );
+            }
+            self.buffers = Self::new_buffers(ctx, self.instancing, capacity);
+        }
+
+        ctx.apply_pipeline(&self.pipeline);
+        self.uniforms[..16].copy_from_slice(projection);
+
+        let mut start = 0;
+        for (texture, count) in self.runs.drain(..) {
+            let run = &self.instances[start..start + count];
+            start += count;
+
+            if self.instancing {
+                self.buffers.instances.update(ctx, run);
+            } else {
+                let expanded: Vec<Instance> =
+                    run.iter().flat_map(|i| std::iter::repeat_n(*i, 4)).collect();
+                self.buffers.instances.update(ctx, &expanded);
+            }
+
+            ctx.apply_bindings(&Bindings {
+                vertex_buffers: vec![*self.buffers.vertex, *self.buffers.instances],
+                index_buffer: *self.buffers.index,
+                images: vec![texture],
+            });
+            // the whole Vec, sized from the shader uniforms in with_fragment_shader
+            unsafe {
+                ctx.apply_uniforms_from_bytes(
+                    self.uniforms.as_ptr() as *const u8,
+                    self.uniforms.len() * std::mem::size_of::<f32>(),
+                );
+            }
+            if self.instancing {
+                ctx.draw(0, 6, count as i32);
+            } else {
+                ctx.draw(0, 6 * count as i32, 1);
+            }
+        }
+        self.instances.clear();
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::gl::{
+    glBeginQuery, glDeleteQueries, glEndQuery, glGenQueries, glGetQueryObjectiv,
+    glGetQueryObjectui64v, GLuint, GL_QUERY_RESULT, GL_QUERY_RESULT_AVAILABLE, GL_TIME_ELAPSED,
+};
+
+/// Frames kept for the frame time percentiles
+const HISTORY: usize = 240;
+
+/// What the renderer did during one frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameCounters {
+    pub draw_calls: u32,
+    pub pipeline_changes: u32,
+    pub binding_changes: u32,
+    pub uniform_updates: u32,
+    /// `begin_pass` calls, default pass included
+    pub passes: u32,
+    pub buffer_uploads: u32,
+    pub texture_uploads: u32,
+    /// Bytes sent by the buffer and texture uploads
+    pub uploaded_bytes: u64,
+}
+
+/// Renderer statistics, updated once per frame by the main loop, see `GraphicsContext::stats`
+pub struct Stats {
+    /// Frames completed since the start
+    pub frames: u64,
+    /// Counters of the last complete frame
+    pub last_frame: FrameCounters,
+    /// Time between the starts of the last two frames, in seconds, 0 before the second frame
+    pub frame_time: f32,
+    /// GPU time of the latest frame whose timer query came back, in seconds.
+    /// `None` unless `GraphicsContext::enable_gpu_timer` succeeded.
+    pub gpu_time: Option<f32>,
+    frame_times: VecDeque<f32>,
+    trace: Option<BufWriter<File>>,
+}
+
+impl Stats {
+    pub(crate) fn new() -> Stats {
+        Stats {
+            frames: 0,
+            last_frame: Default::default(),
+            frame_time: 0.,
+            gpu_time: None,
+            frame_times: VecDeque::with_capacity(HISTORY),
+            trace: None,
+        }
+    }
+
+    /// Frame time in seconds that `percent` of the last 240 frames did not exceed, 0 before the first frame
+    pub fn frame_time_percentile(&self, percent: f32) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+        let mut sorted: Vec<f32> = self.frame_times.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let rank = (percent / 100. * (sorted.len() - 1) as f32).round() as usize;
+        sorted[rank.min(sorted.len() - 1)]
+    }
+
+    /// `frame_time` is the time since the start of the previous frame, ignored before the first one ended
+    pub(crate) fn begin_frame(&mut self, frame_time: f32) {
+        if self.frames == 0 {
+            return;
+        }
+        self.frame_time = frame_time;
+        if self.frame_times.len() == HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    /// `duration` goes from the start of the frame to the end of its buffer swap
+    pub(crate) fn end_frame(&mut self, counters: FrameCounters, duration: f32, gpu_time: Option<f32>) {
+        self.frames += 1;
+        self.last_frame = counters;
+        if gpu_time.is_some() {
+            self.gpu_time = gpu_time;
+        }
+
+        if let Some(trace) = &mut self.trace {
+            let c = counters;
+            let written = writeln!(
+                trace,
+                "{},{:.3},{},{},{},{},{},{},{},{},{}",
+                self.frames,
+                duration * 1000.,
+                gpu_time.map_or(String::new(), |t| format!("{:.3}", t * 1000.)),
+                c.draw_calls,
+                c.pipeline_changes,
+                c.binding_changes,
+                c.uniform_updates,
+                c.passes,
+                c.buffer_uploads,
+                c.texture_uploads,
+                c.uploaded_bytes,
+            );
+            if let Err(e) = written {
+                eprintln!("winopengl: stats trace stopped: {}", e);
+                self.trace = None;
+            }
+        }
+    }
+
+    pub(crate) fn start_trace(&mut self, path: &Path) -> io::Result<()> {
+        let mut trace = BufWriter::new(File::create(path)?);
+        writeln!(
+            trace,
+            "frame,frame_ms,gpu_ms,draw_calls,pipeline_changes,binding_changes,uniform_updates,passes,buffer_uploads,texture_uploads,uploaded_bytes"
+        )?;
+        self.trace = Some(trace);
+        Ok(())
+    }
+
+    pub(crate) fn stop_trace(&mut self) -> io::Result<()> {
+        match self.trace.take() {
+            Some(mut trace) => trace.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// GL_TIME_ELAPSED queries spanning one frame each.
+/// Results arrive a few frames late, queries are reused once read.
+pub(crate) struct GpuTimer {
+    free: Vec<GLuint>,
+    pending: VecDeque<GLuint>,
+    running: Option<GLuint>,
+}
+
+impl GpuTimer {
+    pub fn new() -> GpuTimer {
+        GpuTimer {
+            free: vec![],
+            pending: VecDeque::new(),
+            running: None,
+        }
+    }
+
+    /// Close the query of the frame that ended, start the next one.
+    /// Returns the GPU time of the newest finished frame, in seconds.
+    pub fn frame(&mut self) -> Option<f32> {
+        unsafe {
+            if let Some(query) = self.running.take() {
+                glEndQuery(GL_TIME_ELAPSED);
+                self.pending.push_back(query);
+            }
+
+            let mut gpu_time = None;
+            while let Some(&query) = self.pending.front() {
+                let mut available = 0;
+                glGetQueryObjectiv(query, GL_QUERY_RESULT_AVAILABLE, &mut available);
+                if available == 0 {
+                    break;
+                }
+                let mut nanoseconds = 0;
+                glGetQueryObjectui64v(query, GL_QUERY_RESULT, &mut nanoseconds);
+                gpu_time = Some(nanoseconds as f32 / 1e9);
+                self.free.push(query);
+                self.pending.pop_front();
+            }
+
+            let query = self.free.pop().unwrap_or_else(|| {
+                let mut query = 0;
+                glGenQueries(1, &mut query);
+                query
+            });
+            glBeginQuery(GL_TIME_ELAPSED, query);
+            self.running = Some(query);
+
+            gpu_time
+        }
+    }
+
+    pub fn delete(self) {
+        unsafe {
+            if self.running.is_some() {
+                glEndQuery(GL_TIME_ELAPSED);
+            }
+            let queries: Vec<GLuint> = self
+                .free
+                .into_iter()
+                .chain(self.pending)
+                .chain(self.running)
+                .collect();
+            glDeleteQueries(queries.len() as i32, queries.as_ptr());
+        }
+    }
+}
@@ -1,38 +1,182 @@
 use crate::gl::{
-    GLuint, 
-    GL_RGB, 
-    GL_UNSIGNED_BYTE, 
-    GL_CLAMP_TO_EDGE, 
-    GL_LINEAR, 
-    GL_UNPACK_ALIGNMENT, 
-    GL_TEXTURE_2D, 
-    GL_TEXTURE_SWIZZLE_A, 
-    GL_ALPHA, 
+    GLuint,
+    GLenum,
+    GL_RGB,
+    GL_RGBA,
+    GL_DEPTH_COMPONENT,
+    GL_UNSIGNED_INT,
+    GL_UNSIGNED_BYTE,
+    GL_CLAMP_TO_EDGE,
+    GL_REPEAT,
+    GL_MIRRORED_REPEAT,
+    GL_LINEAR,
+    GL_NEAREST,
+    GL_LINEAR_MIPMAP_LINEAR,
+    GL_NEAREST_MIPMAP_NEAREST,
+    GL_UNPACK_ALIGNMENT,
+    GL_TEXTURE_2D,
+    GL_ALPHA,
+    GL_RED,
+    GL_R8,
+    GL_ZERO,
+    GL_TEXTURE_SWIZZLE_R,
+    GL_TEXTURE_SWIZZLE_G,
+    GL_TEXTURE_SWIZZLE_B,
+    GL_TEXTURE_SWIZZLE_A,
     GL_TEXTURE_WRAP_S,
-    GL_TEXTURE_WRAP_T, 
-    GL_TEXTURE_MIN_FILTER, 
+    GL_TEXTURE_WRAP_T,
+    GL_TEXTURE_MIN_FILTER,
     GL_TEXTURE_MAG_FILTER,
-    glGenTextures, 
-    glPixelStorei, 
-    glTexParameteri, glTexImage2D
+    glGenTextures,
+    glDeleteTextures,
+    glGenerateMipmap,
+    glPixelStorei,
+    glTexParameteri, glTexImage2D, glTexSubImage2D
 };
-use crate::graphics::GraphicsContext;
+use crate::graphics::{Features, GraphicsContext, Id};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    RGB8,
+    RGBA8,
+    /// Single 8 bits channel, read as `a` in shaders (Alpha8, R8 on other APIs),
+    /// see `Features::red_textures`
+    Alpha8,
+    /// Only usable as a `RenderPass` depth attachment
+    Depth,
+}
+
+/// Converts to (internal_format, format, pixel_type)
+impl From<TextureFormat> for (GLenum, GLenum, GLenum) {
+    fn from(format: TextureFormat) -> Self {
+        match format {
+            TextureFormat::RGB8 => (GL_RGB, GL_RGB, GL_UNSIGNED_BYTE),
+            TextureFormat::RGBA8 => (GL_RGBA, GL_RGBA, GL_UNSIGNED_BYTE),
+            TextureFormat::Alpha8 => (GL_ALPHA, GL_ALPHA, GL_UNSIGNED_BYTE),
+            TextureFormat::Depth => (GL_DEPTH_COMPONENT, GL_DEPTH_COMPONENT, GL_UNSIGNED_INT),
+        }
+    }
+}
+
+/// (internal_format, format, pixel_type) the context can store `format` with
+fn gl_format(format: TextureFormat, features: &Features) -> (GLenum, GLenum, GLenum) {
+    match format {
+        TextureFormat::Alpha8 if features.red_textures => (GL_R8, GL_RED, GL_UNSIGNED_BYTE),
+        _ => format.into(),
+    }
+}
+
+impl TextureFormat {
+    /// Size in bytes of one pixel
+    pub fn size(self) -> u32 {
+        match self {
+            TextureFormat::RGB8 => 3,
+            TextureFormat::RGBA8 => 4,
+            TextureFormat::Alpha8 => 1,
+            TextureFormat::Depth => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FilterMode {
+    Linear,
+    Nearest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureWrap {
+    Clamp,
+    Repeat,
+    Mirror,
+}
+
+impl From<TextureWrap> for GLenum {
+    fn from(wrap: TextureWrap) -> Self {
+        match wrap {
+            TextureWrap::Clamp => GL_CLAMP_TO_EDGE,
+            TextureWrap::Repeat => GL_REPEAT,
+            TextureWrap::Mirror => GL_MIRRORED_REPEAT,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureParams {
+    pub format: TextureFormat,
+    pub wrap: TextureWrap,
+    pub filter: FilterMode,
+    pub width: u32,
+    pub height: u32,
+    /// Generate mipmaps on upload and on every update.
+    /// GLES2 only supports them on power of two sizes.
+    pub mipmaps: bool,
+}
+
+impl Default for TextureParams {
+    fn default() -> Self {
+        TextureParams {
+            format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Clamp,
+            filter: FilterMode::Linear,
+            width: 0,
+            height: 0,
+            mipmaps: false,
+        }
+    }
+}
+
+/// What the context keeps of a texture, for the leak report
+pub(crate) struct TextureInternal {
+    pub(crate) texture: GLuint,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Texture {
+    pub(crate) id: Id,
     pub texture: GLuint,
     pub width: u32,
     pub height: u32,
+    pub format: TextureFormat,
+    mipmaps: bool,
 }
 
 impl Texture {
+    /// Upload `bytes`, tightly packed rows of `params.format` pixels, top row first.
     pub fn new(
         ctx: &mut GraphicsContext,
         bytes: &[u8],
-        width: u32,
-        height: u32,
+        params: TextureParams,
     ) -> Texture {
-        assert_eq!((3*width*height) as usize, bytes.len());
+        assert_eq!(
+            (params.format.size() * params.width * params.height) as usize,
+            bytes.len(),
+            "Texture data size does not match {}x{} {:?}",
+            params.width,
+            params.height,
+            params.format
+        );
+        Self::new_internal(ctx, bytes.as_ptr(), params)
+    }
+
+    /// Empty texture meant to be attached to a `RenderPass` and drawn into.
+    /// Passes only draw into the first level, so `params.mipmaps` must be false.
+    pub fn new_render_texture(
+        ctx: &mut GraphicsContext,
+        params: TextureParams,
+    ) -> Texture {
+        assert!(!params.mipmaps, "Render textures cannot have mipmaps, passes only draw into the first level");
+        Self::new_internal(ctx, std::ptr::null(), params)
+    }
+
+    fn new_internal(
+        ctx: &mut GraphicsContext,
+        bytes: *const u8,
+        params: TextureParams,
+    ) -> Texture {
+        let (internal_format, format, pixel_type) = gl_format(params.format, &ctx.features);
         ctx.cache.store_texture_binding(0);
 
         let mut texture: GLuint = 0;
@@ -42,31 +186,131 @@ impl Texture {
             ctx.cache.bind_texture(0, texture);
             glPixelStorei(GL_UNPACK_ALIGNMENT, 1); // miniquad always uses row alignment of 1
 
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_SWIZZLE_A, GL_ALPHA as _);
-
             glTexImage2D(
                 GL_TEXTURE_2D,
                 0,
-                GL_RGB as i32,
-                width as i32,
-                height as i32,
+                internal_format as i32,
+                params.width as i32,
+                params.height as i32,
                 0,
-                GL_RGB,
-                GL_UNSIGNED_BYTE,
-                bytes.as_ptr() as *const _,
+                format,
+                pixel_type,
+                bytes as *const _,
             );
 
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as i32);
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as i32);
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, GL_LINEAR as i32);
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR as i32);
+            if internal_format == GL_R8 {
+                // read as (0, 0, 0, r) like GL_ALPHA
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_SWIZZLE_R, GL_ZERO as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_SWIZZLE_G, GL_ZERO as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_SWIZZLE_B, GL_ZERO as i32);
+                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_SWIZZLE_A, GL_RED as i32);
+            }
+
+            if params.mipmaps && !bytes.is_null() {
+                glGenerateMipmap(GL_TEXTURE_2D);
+            }
+
+            let wrap: GLenum = params.wrap.into();
+            let (min_filter, mag_filter) = match (params.filter, params.mipmaps) {
+                (FilterMode::Linear, false) => (GL_LINEAR, GL_LINEAR),
+                (FilterMode::Linear, true) => (GL_LINEAR_MIPMAP_LINEAR, GL_LINEAR),
+                (FilterMode::Nearest, false) => (GL_NEAREST, GL_NEAREST),
+                (FilterMode::Nearest, true) => (GL_NEAREST_MIPMAP_NEAREST, GL_NEAREST),
+            };
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, wrap as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, wrap as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MIN_FILTER, min_filter as i32);
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, mag_filter as i32);
         }
         ctx.cache.restore_texture_binding(0);
+        if !bytes.is_null() {
+            ctx.counters.texture_uploads += 1;
+            ctx.counters.uploaded_bytes += (params.format.size() * params.width * params.height) as u64;
+        }
 
         Texture {
+            id: ctx.textures.insert(TextureInternal {
+                texture,
+                width: params.width,
+                height: params.height,
+            }),
             texture,
+            width: params.width,
+            height: params.height,
+            format: params.format,
+            mipmaps: params.mipmaps,
+        }
+    }
+
+    /// Free the GL texture, render passes using it must be deleted first
+    pub fn delete(self, ctx: &mut GraphicsContext) {
+        ctx.textures.remove(self.id);
+        ctx.cache.forget_texture(self.texture);
+        unsafe {
+            glDeleteTextures(1, &self.texture as *const _);
+        }
+    }
+
+    /// Replace the whole content, `bytes` laid out like in `Texture::new`
+    pub fn update(&self, ctx: &mut GraphicsContext, bytes: &[u8]) {
+        self.update_region(ctx, 0, 0, self.width, self.height, bytes);
+    }
+
+    /// Replace the `width`x`height` rectangle starting at `x`, `y`
+    pub fn update_region(
+        &self,
+        ctx: &mut GraphicsContext,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        bytes: &[u8],
+    ) {
+        assert!(
+            x.checked_add(width).is_some_and(|right| right <= self.width)
+                && y.checked_add(height).is_some_and(|bottom| bottom <= self.height),
+            "Region {}x{} at {},{} is outside of the {}x{} texture",
             width,
             height,
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        assert_eq!(
+            (self.format.size() * width * height) as usize,
+            bytes.len(),
+            "Region data size does not match {}x{} {:?}",
+            width,
+            height,
+            self.format
+        );
+
+        ctx.textures.check(self.id);
+        let (_, format, pixel_type) = gl_format(self.format, &ctx.features);
+        ctx.cache.store_texture_binding(0);
+        ctx.cache.bind_texture(0, self.texture);
+
+        unsafe {
+            glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
+            glTexSubImage2D(
+                GL_TEXTURE_2D,
+                0,
+                x as i32,
+                y as i32,
+                width as i32,
+                height as i32,
+                format,
+                pixel_type,
+                bytes.as_ptr() as *const _,
+            );
+            if self.mipmaps {
+                glGenerateMipmap(GL_TEXTURE_2D);
+            }
         }
+
+        ctx.cache.restore_texture_binding(0);
+        ctx.counters.texture_uploads += 1;
+        ctx.counters.uploaded_bytes += bytes.len() as u64;
     }
 }
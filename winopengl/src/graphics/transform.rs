@@ -0,0 +1,134 @@
+/// Column major 4x4 matrix, the layout `UniformType::Mat4` uploads
+pub type Mat4 = [f32; 16];
+
+pub const IDENTITY: Mat4 = [
+    1., 0., 0., 0., //
+    0., 1., 0., 0., //
+    0., 0., 1., 0., //
+    0., 0., 0., 1.,
+];
+
+/// `a * b`, applying `b` first
+pub fn mat4_mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut res = [0.; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            res[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    res
+}
+
+/// Orthographic projection of the `left..right`, `bottom..top` rectangle on the whole viewport
+pub fn ortho(left: f32, right: f32, bottom: f32, top: f32) -> Mat4 {
+    let (w, h) = (right - left, top - bottom);
+    [
+        2. / w, 0., 0., 0., //
+        0., 2. / h, 0., 0., //
+        0., 0., -1., 0., //
+        -(right + left) / w, -(top + bottom) / h, 0., 1.,
+    ]
+}
+
+/// Scale, then rotation, then translation of a sprite.
+/// Flipping is a negative scale.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform2D {
+    pub translation: (f32, f32),
+    /// Radians, counter clockwise
+    pub rotation: f32,
+    pub scale: (f32, f32),
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Transform2D {
+            translation: (0., 0.),
+            rotation: 0.,
+            scale: (1., 1.),
+        }
+    }
+}
+
+impl Transform2D {
+    pub fn from_translation(x: f32, y: f32) -> Transform2D {
+        Transform2D {
+            translation: (x, y),
+            ..Default::default()
+        }
+    }
+
+    pub fn rotated(self, rotation: f32) -> Transform2D {
+        Transform2D {
+            rotation: self.rotation + rotation,
+            ..self
+        }
+    }
+
+    pub fn scaled(self, x: f32, y: f32) -> Transform2D {
+        Transform2D {
+            scale: (self.scale.0 * x, self.scale.1 * y),
+            ..self
+        }
+    }
+
+    /// Mirror horizontally and/or vertically, before the rotation
+    pub fn flipped(self, x: bool, y: bool) -> Transform2D {
+        let sign = |flip| if flip { -1. } else { 1. };
+        self.scaled(sign(x), sign(y))
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        let (s, c) = self.rotation.sin_cos();
+        let (sx, sy) = self.scale;
+        let (tx, ty) = self.translation;
+        [
+            c * sx, s * sx, 0., 0., //
+            -s * sy, c * sy, 0., 0., //
+            0., 0., 1., 0., //
+            tx, ty, 0., 1.,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(m: &Mat4, (x, y): (f32, f32)) -> (f32, f32) {
+        (m[0] * x + m[4] * y + m[12], m[1] * x + m[5] * y + m[13])
+    }
+
+    fn assert_near(a: (f32, f32), b: (f32, f32)) {
+        assert!((a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn mul_applies_the_right_matrix_first() {
+        let scale = Transform2D::default().scaled(2., 3.).matrix();
+        let translate = Transform2D::from_translation(1., 1.).matrix();
+        assert_eq!(mat4_mul(&IDENTITY, &scale), scale);
+        assert_near(apply(&mat4_mul(&translate, &scale), (1., 1.)), (3., 4.));
+        assert_near(apply(&mat4_mul(&scale, &translate), (1., 1.)), (4., 6.));
+    }
+
+    #[test]
+    fn ortho_maps_the_rectangle_to_clip_space() {
+        let m = ortho(-2., 6., 1., 5.);
+        assert_near(apply(&m, (-2., 1.)), (-1., -1.));
+        assert_near(apply(&m, (6., 5.)), (1., 1.));
+        assert_near(apply(&m, (2., 3.)), (0., 0.));
+    }
+
+    #[test]
+    fn scale_and_flip_come_before_rotation_and_translation() {
+        let t = Transform2D::from_translation(10., 20.)
+            .rotated(std::f32::consts::FRAC_PI_2)
+            .scaled(2., 1.)
+            .flipped(true, false);
+        // (1, 0) scaled and flipped to (-2, 0), turned a quarter counter clockwise to (0, -2), then moved
+        assert_near(apply(&t.matrix(), (1., 0.)), (10., 18.));
+        // (0, 1) keeps its length, turned to (-1, 0)
+        assert_near(apply(&t.matrix(), (0., 1.)), (9., 20.));
+    }
+}
@@ -1,7 +1,9 @@
 pub mod conf;
+mod error;
 mod event;
 pub mod graphics;
 pub mod native;
+pub use error::Error;
 pub use event::*;
 pub use graphics::*;
 pub use native::{gl, NativeDisplay};
@@ -33,12 +35,29 @@ impl GraphicsContext {
     {
         self.display_mut().set_title(title);
     }
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.display_mut().set_fullscreen(fullscreen);
+    }
 }
 
 /// Start miniquad.
-pub fn start<F>(conf: conf::Conf, f: F)
+/// Returns once the application quits, or with the reason the window, context or handler could not be created.
+pub fn start<F>(conf: conf::Conf, f: F) -> Result<(), Error>
 where
-    F: 'static + FnOnce(&mut GraphicsContext) -> Box<dyn EventHandler>,
+    F: 'static + FnOnce(&mut GraphicsContext) -> Result<Box<dyn EventHandler>, Error>,
 {
-    native::windows::run(&conf, f);
-}
\ No newline at end of file
+    if let Some(frames) = conf.headless_frames {
+        #[cfg(target_os = "linux")]
+        return native::headless::run(&conf, frames, f);
+        #[cfg(not(target_os = "linux"))]
+        return Err(Error::Context(format!(
+            "headless rendering needs EGL, only available on linux ({} frames requested)",
+            frames
+        )));
+    }
+
+    #[cfg(target_os = "windows")]
+    return native::windows::run(&conf, f);
+    #[cfg(target_os = "linux")]
+    return native::linux_x11::run(&conf, f);
+}
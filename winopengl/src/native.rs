@@ -7,12 +7,39 @@ pub(crate) struct NativeDisplayData {
 }
 
 
+/// Load the GL functions of the current context and detect what it can do, shared by the backends
+pub(crate) unsafe fn new_graphics_context<T: FnMut(&str) -> Option<unsafe extern "C" fn()>>(
+    conf: &crate::conf::Conf,
+    (width, height): (i32, i32),
+    swap_interval: Option<i32>,
+    fell_back: bool,
+    mut getprocaddr: T,
+) -> Result<crate::GraphicsContext, gl::GlLoadError> {
+    let missing = gl::load_gl_funcs(&mut getprocaddr)?;
+    let info = crate::ContextInfo::query(conf.gl_profile, swap_interval, fell_back);
+    let features = crate::Features::detect(&info, missing);
+    if conf.gl_debug {
+        gl::enable_debug(features.debug_output, getprocaddr);
+    }
+    Ok(crate::GraphicsContext::new(width, height, info, features))
+}
+
 pub trait NativeDisplay: std::any::Any {
     fn set_title(&mut self, title: String);
     fn order_quit(&mut self);
+    /// Borderless window covering the monitor, or back to the previous window
+    fn set_fullscreen(&mut self, fullscreen: bool);
     fn as_any(&mut self) -> &mut dyn std::any::Any;
 }
 
 pub mod module;
+pub(crate) mod pacing;
+#[cfg(target_os = "windows")]
 pub mod windows;
+#[cfg(target_os = "linux")]
+pub mod linux_x11;
+#[cfg(target_os = "linux")]
+pub mod egl;
+#[cfg(target_os = "linux")]
+pub mod headless;
 pub mod gl;
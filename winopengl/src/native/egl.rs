@@ -1,4 +1,4 @@
-#![allow(non_camel_case_types, non_snake_case)]
+#![allow(non_camel_case_types, non_snake_case, clippy::missing_safety_doc)]
 
 use crate::native::module;
 
@@ -6,6 +6,7 @@ pub use core::ptr::null_mut;
 
 pub const EGL_SUCCESS: u32 = 12288;
 
+pub const EGL_PBUFFER_BIT: u32 = 1;
 pub const EGL_WINDOW_BIT: u32 = 4;
 
 pub const EGL_ALPHA_SIZE: u32 = 12321;
@@ -19,13 +20,27 @@ pub const EGL_WIDTH: u32 = 12375;
 pub const EGL_HEIGHT: u32 = 12374;
 pub const EGL_SURFACE_TYPE: u32 = 12339;
 pub const EGL_NONE: u32 = 12344;
+pub const EGL_EXTENSIONS: u32 = 12373;
 pub const EGL_CONTEXT_CLIENT_VERSION: u32 = 12440;
+pub const EGL_CONTEXT_MINOR_VERSION: u32 = 0x30FB;
+pub const EGL_CONTEXT_OPENGL_DEBUG: u32 = 0x31B0;
+pub const EGL_TRUE: u32 = 1;
+pub const EGL_RENDERABLE_TYPE: u32 = 0x3040;
+pub const EGL_OPENGL_ES2_BIT: u32 = 0x0004;
+pub const EGL_OPENGL_ES3_BIT_KHR: u32 = 0x0040;
+pub const EGL_SAMPLES: u32 = 12337;
+pub const EGL_SAMPLE_BUFFERS: u32 = 12338;
+pub const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
 
+pub type EGLNativeDisplayType = *mut ::std::os::raw::c_void;
+pub type EGLNativePixmapType = ::std::os::raw::c_ulong;
+pub type EGLNativeWindowType = ::std::os::raw::c_ulong;
 pub type NativeDisplayType = EGLNativeDisplayType;
 pub type NativePixmapType = EGLNativePixmapType;
 pub type NativeWindowType = EGLNativeWindowType;
 pub type EGLint = i32;
 pub type EGLBoolean = ::std::os::raw::c_uint;
+pub type EGLenum = ::std::os::raw::c_uint;
 pub type EGLDisplay = *mut ::std::os::raw::c_void;
 pub type EGLConfig = *mut ::std::os::raw::c_void;
 pub type EGLSurface = *mut ::std::os::raw::c_void;
@@ -160,6 +175,13 @@ pub type PFNEGLSURFACEATTRIBPROC = ::std::option::Option<
         value: EGLint,
     ) -> EGLBoolean,
 >;
+pub type PFNEGLGETPLATFORMDISPLAYEXTPROC = ::std::option::Option<
+    unsafe extern "C" fn(
+        platform: EGLenum,
+        native_display: *mut ::std::os::raw::c_void,
+        attrib_list: *const EGLint,
+    ) -> EGLDisplay,
+>;
 pub type PFNEGLSWAPINTERVALPROC =
     ::std::option::Option<unsafe extern "C" fn(dpy: EGLDisplay, interval: EGLint) -> EGLBoolean>;
 
@@ -238,54 +260,190 @@ impl LibEgl {
 pub enum EglError {
     NoDisplay,
     InitializeFailed,
+    NoConfig,
     CreateContextFailed,
+    CreateSurfaceFailed,
 }
 
 pub struct Egl {}
 
-pub unsafe fn create_egl_context(
+pub unsafe fn get_display(
     egl: &mut LibEgl,
     display: *mut std::ffi::c_void,
-    alpha: bool,
-) -> Result<(EGLContext, EGLConfig, EGLDisplay), EglError> {
+) -> Result<EGLDisplay, EglError> {
     let display = (egl.eglGetDisplay.unwrap())(display as _);
-    if display == /* EGL_NO_DISPLAY */ null_mut() {
+    if display.is_null() /* EGL_NO_DISPLAY */ {
         return Err(EglError::NoDisplay);
     }
+    Ok(display)
+}
 
-    if (egl.eglInitialize.unwrap())(display, null_mut(), null_mut()) == 0 {
+/// Display not tied to any window system, for offscreen rendering only.
+/// Uses Mesa's surfaceless platform when available, the default display otherwise.
+pub unsafe fn get_surfaceless_display(egl: &mut LibEgl) -> Result<EGLDisplay, EglError> {
+    // client extensions, queried without a display
+    if has_extension(egl, /* EGL_NO_DISPLAY */ null_mut(), "EGL_MESA_platform_surfaceless") {
+        let name = std::ffi::CString::new("eglGetPlatformDisplayEXT").unwrap();
+        let get_platform_display: PFNEGLGETPLATFORMDISPLAYEXTPROC =
+            std::mem::transmute((egl.eglGetProcAddress.unwrap())(name.as_ptr()));
+        if let Some(get_platform_display) = get_platform_display {
+            let display = get_platform_display(
+                EGL_PLATFORM_SURFACELESS_MESA,
+                /* EGL_DEFAULT_DISPLAY */ null_mut(),
+                null_mut(),
+            );
+            if !display.is_null() {
+                return Ok(display);
+            }
+        }
+    }
+
+    get_display(egl, /* EGL_DEFAULT_DISPLAY */ null_mut())
+}
+
+/// GLES context of version `es_version`, or 2.0 if the driver refuses it.
+/// Also returns whether 2.0 was used instead.
+pub unsafe fn create_egl_context(
+    egl: &mut LibEgl,
+    display: EGLDisplay,
+    alpha: bool,
+    surface_type: u32,
+    sample_count: i32,
+    es_version: (i32, i32),
+    debug: bool,
+) -> Result<(EGLContext, EGLConfig, bool), EglError> {
+    let (mut egl_major, mut egl_minor): (EGLint, EGLint) = (0, 0);
+    if (egl.eglInitialize.unwrap())(display, &mut egl_major, &mut egl_minor) == 0 {
         return Err(EglError::InitializeFailed);
     }
+    // EGL 1.4 without EGL_KHR_create_context rejects any attribute but the major version
+    let create_context = (egl_major, egl_minor) >= (1, 5)
+        || has_extension(egl, display, "EGL_KHR_create_context");
+    // EGL_CONTEXT_OPENGL_DEBUG is EGL 1.5 only, a driver refusing it still gets a plain context
+    let debug_attempts: &[bool] = if debug && (egl_major, egl_minor) >= (1, 5) {
+        &[true, false]
+    } else {
+        &[false]
+    };
 
+    let mut attempts = vec![es_version];
+    if es_version != (2, 0) {
+        attempts.push((2, 0));
+    }
+    let mut configs: Vec<(u32, EGLConfig)> = vec![];
+    for (i, (major, minor)) in attempts.into_iter().enumerate() {
+        let renderable_type = if major >= 3 {
+            EGL_OPENGL_ES3_BIT_KHR
+        } else {
+            EGL_OPENGL_ES2_BIT
+        };
+        let config = match configs.iter().find(|(r, _)| *r == renderable_type) {
+            Some(&(_, config)) => config,
+            None => match choose_config(egl, display, alpha, surface_type, sample_count, renderable_type) {
+                Some(config) => {
+                    configs.push((renderable_type, config));
+                    config
+                }
+                None => continue,
+            },
+        };
+
+        let mut ctx_attributes = vec![EGL_CONTEXT_CLIENT_VERSION, major as u32];
+        if minor != 0 {
+            if !create_context {
+                continue;
+            }
+            ctx_attributes.extend_from_slice(&[EGL_CONTEXT_MINOR_VERSION, minor as u32]);
+        }
+        for &debug in debug_attempts {
+            let mut attributes = ctx_attributes.clone();
+            if debug {
+                attributes.extend_from_slice(&[EGL_CONTEXT_OPENGL_DEBUG, EGL_TRUE]);
+            }
+            attributes.push(EGL_NONE);
+            let context = (egl.eglCreateContext.unwrap())(
+                display,
+                config,
+                /* EGL_NO_CONTEXT */ null_mut(),
+                attributes.as_ptr() as _,
+            );
+            if !context.is_null() {
+                return Ok((context, config, i > 0));
+            }
+        }
+    }
+    if configs.is_empty() {
+        return Err(EglError::NoConfig);
+    }
+    Err(EglError::CreateContextFailed)
+}
+
+/// `name` is in the extension string of `display`
+unsafe fn has_extension(egl: &mut LibEgl, display: EGLDisplay, name: &str) -> bool {
+    let extensions = (egl.eglQueryString.unwrap())(display, EGL_EXTENSIONS as _);
+    !extensions.is_null()
+        && std::ffi::CStr::from_ptr(extensions)
+            .to_string_lossy()
+            .split_whitespace()
+            .any(|ext| ext == name)
+}
+
+/// RGB8 config able to run contexts of `renderable_type`, multisampled when asked and available
+unsafe fn choose_config(
+    egl: &mut LibEgl,
+    display: EGLDisplay,
+    alpha: bool,
+    surface_type: u32,
+    sample_count: i32,
+    renderable_type: u32,
+) -> Option<EGLConfig> {
     let alpha_size = if alpha { 8 } else { 0 };
     #[rustfmt::skip]
-    let cfg_attributes = vec![
-        EGL_SURFACE_TYPE, EGL_WINDOW_BIT,
+    let mut cfg_attributes = vec![
+        EGL_SURFACE_TYPE, surface_type,
+        EGL_RENDERABLE_TYPE, renderable_type,
         EGL_RED_SIZE, 8,
         EGL_GREEN_SIZE, 8,
         EGL_BLUE_SIZE, 8,
         EGL_ALPHA_SIZE, alpha_size,
         EGL_DEPTH_SIZE, 16,
         EGL_STENCIL_SIZE, 0,
-        EGL_NONE,
     ];
     let mut available_cfgs: Vec<EGLConfig> = vec![null_mut(); 32];
-    let mut cfg_count = 0;
+    let mut choose = |attributes: &[u32]| {
+        let attributes: Vec<u32> = attributes.iter().copied().chain([EGL_NONE]).collect();
+        let mut cfg_count: EGLint = 0;
+        let chosen = (egl.eglChooseConfig.unwrap())(
+            display,
+            attributes.as_ptr() as _,
+            available_cfgs.as_mut_ptr() as _,
+            available_cfgs.len() as _,
+            &mut cfg_count,
+        );
+        if chosen == 0 {
+            0
+        } else {
+            (cfg_count.max(0) as usize).min(available_cfgs.len())
+        }
+    };
 
-    (egl.eglChooseConfig.unwrap())(
-        display,
-        cfg_attributes.as_ptr() as _,
-        available_cfgs.as_ptr() as _,
-        32,
-        &mut cfg_count as *mut _ as *mut _,
-    );
-    assert!(cfg_count > 0);
-    assert!(cfg_count <= 32);
+    let mut cfg_count = 0;
+    if sample_count > 1 {
+        let mut multisampled = cfg_attributes.clone();
+        multisampled.extend_from_slice(&[EGL_SAMPLE_BUFFERS, 1, EGL_SAMPLES, sample_count as u32]);
+        cfg_count = choose(&multisampled);
+    }
+    if cfg_count == 0 {
+        // no MSAA then
+        cfg_attributes.extend_from_slice(&[EGL_SAMPLE_BUFFERS, 0]);
+        cfg_count = choose(&cfg_attributes);
+    }
+    if cfg_count == 0 {
+        return None;
+    }
 
     // find config with 8-bit rgb buffer if available, ndk sample does not trust egl spec
-    let mut config: EGLConfig = null_mut();
-    let mut exact_cfg_found = false;
-    for c in &mut available_cfgs[0..cfg_count] {
+    for c in &available_cfgs[0..cfg_count] {
         let mut r: i32 = 0;
         let mut g: i32 = 0;
         let mut b: i32 = 0;
@@ -302,24 +460,24 @@ pub unsafe fn create_egl_context(
             && (alpha_size == 0 || a == alpha_size as _)
             && d == 16
         {
-            exact_cfg_found = true;
-            config = *c;
-            break;
+            return Some(*c);
         }
     }
-    if !exact_cfg_found {
-        config = available_cfgs[0];
-    }
-    let ctx_attributes = vec![EGL_CONTEXT_CLIENT_VERSION, 2, EGL_NONE];
-    let context = (egl.eglCreateContext.unwrap())(
-        display,
-        config,
-        /* EGL_NO_CONTEXT */ null_mut(),
-        ctx_attributes.as_ptr() as _,
-    );
-    if context.is_null() {
-        return Err(EglError::CreateContextFailed);
-    }
+    Some(available_cfgs[0])
+}
 
-    return Ok((context, config, display));
+pub unsafe fn create_pbuffer_surface(
+    egl: &mut LibEgl,
+    display: EGLDisplay,
+    config: EGLConfig,
+    width: i32,
+    height: i32,
+) -> Result<EGLSurface, EglError> {
+    let attributes = [EGL_WIDTH, width as u32, EGL_HEIGHT, height as u32, EGL_NONE];
+    let surface =
+        (egl.eglCreatePbufferSurface.unwrap())(display, config, attributes.as_ptr() as _);
+    if surface.is_null() {
+        return Err(EglError::CreateSurfaceFailed);
+    }
+    Ok(surface)
 }
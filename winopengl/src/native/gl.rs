@@ -101,11 +101,13 @@ pub type GLintptr = khronos_intptr_t;
 
 pub type GLfloat = f32;
 pub type GLclampf = f32;
+pub type GLuint64 = u64;
 
 pub const GL_INFO_LOG_LENGTH: u32 = 0x8B84;
 pub const GL_VERTEX_SHADER: u32 = 0x8B31;
 pub const GL_INCR: u32 = 0x1E02;
 pub const GL_STATIC_DRAW: u32 = 0x88E4;
+pub const GL_STREAM_DRAW: u32 = 0x88E0;
 pub const GL_ELEMENT_ARRAY_BUFFER: u32 = 0x8893;
 pub const GL_DEPTH_TEST: u32 = 0x0B71;
 pub const GL_LINK_STATUS: u32 = 0x8B82;
@@ -120,6 +122,11 @@ pub const GL_TRIANGLES: u32 = 0x0004;
 pub const GL_UNSIGNED_BYTE: u32 = 0x1401;
 pub const GL_TEXTURE_MAG_FILTER: u32 = 0x2800;
 pub const GL_LINEAR: u32 = 0x2601;
+pub const GL_NEAREST: u32 = 0x2600;
+pub const GL_LINEAR_MIPMAP_LINEAR: u32 = 0x2703;
+pub const GL_NEAREST_MIPMAP_NEAREST: u32 = 0x2700;
+pub const GL_REPEAT: u32 = 0x2901;
+pub const GL_MIRRORED_REPEAT: u32 = 0x8370;
 pub const GL_COLOR_BUFFER_BIT: u32 = 0x00004000;
 pub const GL_TEXTURE_WRAP_T: u32 = 0x2803;
 pub const GL_TEXTURE_WRAP_S: u32 = 0x2802;
@@ -127,6 +134,36 @@ pub const GL_TEXTURE_MIN_FILTER: u32 = 0x2801;
 pub const GL_CCW: u32 = 0x0901;
 pub const GL_RGB: u32 = 0x1907;
 pub const GL_FALSE: u32 = 0;
+pub const GL_SHORT: u32 = 0x1402;
+pub const GL_TRUE: u32 = 1;
+pub const GL_BLEND: u32 = 0x0BE2;
+pub const GL_CULL_FACE: u32 = 0x0B44;
+pub const GL_FRONT: u32 = 0x0404;
+pub const GL_BACK: u32 = 0x0405;
+pub const GL_CW: u32 = 0x0900;
+pub const GL_NEVER: u32 = 0x0200;
+pub const GL_LESS: u32 = 0x0201;
+pub const GL_EQUAL: u32 = 0x0202;
+pub const GL_LEQUAL: u32 = 0x0203;
+pub const GL_GREATER: u32 = 0x0204;
+pub const GL_NOTEQUAL: u32 = 0x0205;
+pub const GL_GEQUAL: u32 = 0x0206;
+pub const GL_ALWAYS: u32 = 0x0207;
+pub const GL_FUNC_ADD: u32 = 0x8006;
+pub const GL_FUNC_SUBTRACT: u32 = 0x800A;
+pub const GL_FUNC_REVERSE_SUBTRACT: u32 = 0x800B;
+pub const GL_ZERO: u32 = 0;
+pub const GL_SRC_COLOR: u32 = 0x0300;
+pub const GL_ONE_MINUS_SRC_COLOR: u32 = 0x0301;
+pub const GL_SRC_ALPHA: u32 = 0x0302;
+pub const GL_ONE_MINUS_SRC_ALPHA: u32 = 0x0303;
+pub const GL_DST_ALPHA: u32 = 0x0304;
+pub const GL_ONE_MINUS_DST_ALPHA: u32 = 0x0305;
+pub const GL_DST_COLOR: u32 = 0x0306;
+pub const GL_ONE_MINUS_DST_COLOR: u32 = 0x0307;
+pub const GL_SRC_ALPHA_SATURATE: u32 = 0x0308;
+pub const GL_POINTS: u32 = 0x0000;
+pub const GL_LINES: u32 = 0x0001;
 pub const GL_UNSIGNED_SHORT: u32 = 0x1403;
 pub const GL_SCISSOR_TEST: u32 = 0x0C11;
 pub const GL_FRAMEBUFFER: u32 = 0x8D40;
@@ -135,8 +172,50 @@ pub const GL_FRAMEBUFFER_BINDING: u32 = 0x8CA6;
 pub const GL_COMPILE_STATUS: u32 = 0x8B81;
 pub const GL_ALPHA: u32 = 6406;
 pub const GL_UNPACK_ALIGNMENT: u32 = 3317;
+pub const GL_PACK_ALIGNMENT: u32 = 3333;
+pub const GL_RGBA: u32 = 0x1908;
+pub const GL_DEPTH_COMPONENT: u32 = 0x1902;
+pub const GL_UNSIGNED_INT: u32 = 0x1405;
+pub const GL_COLOR_ATTACHMENT0: u32 = 0x8CE0;
+pub const GL_DEPTH_ATTACHMENT: u32 = 0x8D00;
+pub const GL_FRAMEBUFFER_COMPLETE: u32 = 0x8CD5;
+pub const GL_TEXTURE_SWIZZLE_R: u32 = 0x8E42;
+pub const GL_TEXTURE_SWIZZLE_G: u32 = 0x8E43;
+pub const GL_TEXTURE_SWIZZLE_B: u32 = 0x8E44;
 pub const GL_TEXTURE_SWIZZLE_A: u32 = 36421;
+pub const GL_RED: u32 = 0x1903;
+pub const GL_R8: u32 = 0x8229;
 pub const GL_VERSION: u32 = 0x1F02;
+pub const GL_RENDERER: u32 = 0x1F01;
+pub const GL_SAMPLES: u32 = 0x80A9;
+pub const GL_MAX_VERTEX_ATTRIB_STRIDE: u32 = 0x82E5;
+pub const GL_TIME_ELAPSED: u32 = 0x88BF;
+pub const GL_CONTEXT_FLAGS: u32 = 0x821E;
+pub const GL_CONTEXT_FLAG_DEBUG_BIT: u32 = 0x00000002;
+pub const GL_QUERY_RESULT: u32 = 0x8866;
+pub const GL_QUERY_RESULT_AVAILABLE: u32 = 0x8867;
+pub const GL_NO_ERROR: u32 = 0;
+pub const GL_INVALID_ENUM: u32 = 0x0500;
+pub const GL_INVALID_VALUE: u32 = 0x0501;
+pub const GL_INVALID_OPERATION: u32 = 0x0502;
+pub const GL_STACK_OVERFLOW: u32 = 0x0503;
+pub const GL_STACK_UNDERFLOW: u32 = 0x0504;
+pub const GL_OUT_OF_MEMORY: u32 = 0x0505;
+pub const GL_INVALID_FRAMEBUFFER_OPERATION: u32 = 0x0506;
+pub const GL_EXTENSIONS: u32 = 0x1F03;
+pub const GL_NUM_EXTENSIONS: u32 = 0x821D;
+pub const GL_DONT_CARE: u32 = 0x1100;
+pub const GL_DEBUG_OUTPUT: u32 = 0x92E0;
+pub const GL_DEBUG_OUTPUT_SYNCHRONOUS: u32 = 0x8242;
+pub const GL_DEBUG_TYPE_ERROR: u32 = 0x824C;
+pub const GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR: u32 = 0x824D;
+pub const GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR: u32 = 0x824E;
+pub const GL_DEBUG_TYPE_PORTABILITY: u32 = 0x824F;
+pub const GL_DEBUG_TYPE_PERFORMANCE: u32 = 0x8250;
+pub const GL_DEBUG_SEVERITY_HIGH: u32 = 0x9146;
+pub const GL_DEBUG_SEVERITY_MEDIUM: u32 = 0x9147;
+pub const GL_DEBUG_SEVERITY_LOW: u32 = 0x9148;
+pub const GL_DEBUG_SEVERITY_NOTIFICATION: u32 = 0x826B;
 pub const WGL_NUMBER_PIXEL_FORMATS_ARB: u32 = 0x2000;
 pub const WGL_SUPPORT_OPENGL_ARB: u32 = 0x2010;
 pub const WGL_DRAW_TO_WINDOW_ARB: u32 = 0x2001;
@@ -167,6 +246,10 @@ macro_rules! gl_loader {
     (
         $(
             fn $fn:ident ( $($arg:ident : $t:ty),* ) -> $res:ty
+        ),*;
+        optional:
+        $(
+            fn $opt_fn:ident ( $($opt_arg:ident : $opt_t:ty),* ) -> $opt_res:ty
         ),*
     ) => {
         mod __pfns {
@@ -175,30 +258,142 @@ macro_rules! gl_loader {
             $(
                 pub static mut $fn: Option<extern "C" fn ($($arg: $t),*) -> $res> = None;
             )*
+            $(
+                pub static mut $opt_fn: Option<extern "C" fn ($($opt_arg: $opt_t),*) -> $opt_res> = None;
+            )*
         }
 
-        $(
-            pub unsafe fn $fn($($arg: $t),*) -> $res {
-                __pfns::$fn.unwrap()( $($arg),* )
-            }
-        )*
+        gl_loader!(@wrappers $(fn $fn($($arg: $t),*) -> $res),*);
+        gl_loader!(@wrappers $(fn $opt_fn($($opt_arg: $opt_t),*) -> $opt_res),*);
 
-        pub fn load_gl_funcs<T: FnMut(&str) -> Option<unsafe extern "C" fn() -> ()>>(mut getprocaddr: T) {
+        /// Whether the driver had each optional function
+        pub mod loaded {
+            $(
+                pub fn $opt_fn() -> bool {
+                    unsafe { super::__pfns::$opt_fn }.is_some()
+                }
+            )*
+        }
+
+        /// Load every function from the current context.
+        /// Fails with all the required functions missing, returns the optional ones missing otherwise.
+        pub fn load_gl_funcs<T: FnMut(&str) -> Option<unsafe extern "C" fn() -> ()>>(
+            mut getprocaddr: T,
+        ) -> Result<Vec<&'static str>, GlLoadError> {
+            let mut missing = Vec::new();
+            $(
+                unsafe {
+                    let proc = getprocaddr(stringify!($fn));
+                    if proc.is_none() {
+                        missing.push(stringify!($fn));
+                    }
+                    __pfns::$fn = ::std::mem::transmute_copy(&proc);
+                }
+            )*
+            if !missing.is_empty() {
+                return Err(GlLoadError { missing });
+            }
             $(
                 unsafe {
-                    let fn_name = stringify!($fn);
-                    __pfns::$fn = ::std::mem::transmute_copy(&getprocaddr(fn_name));
+                    let proc = getprocaddr(stringify!($opt_fn));
+                    if proc.is_none() {
+                        missing.push(stringify!($opt_fn));
+                    }
+                    __pfns::$opt_fn = ::std::mem::transmute_copy(&proc);
                 }
             )*
+            Ok(missing)
         }
     };
+    (@wrappers $(fn $fn:ident ( $($arg:ident : $t:ty),* ) -> $res:ty),*) => {
+        $(
+            // same arguments and contract as the C function
+            #[allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+            pub unsafe fn $fn($($arg: $t),*) -> $res {
+                let res = match __pfns::$fn {
+                    Some(f) => f($($arg),*),
+                    None => not_loaded(stringify!($fn)),
+                };
+                if debug::checking_errors() {
+                    debug::check_error(stringify!($fn), || {
+                        let args: &[&dyn std::fmt::Debug] = &[$(&$arg),*];
+                        args.iter().map(|a| format!("{:?}", a)).collect::<Vec<_>>().join(", ")
+                    });
+                }
+                res
+            }
+        )*
+    };
+}
+
+/// Required GL functions the driver does not have, the context is unusable
+#[derive(Clone, Debug)]
+pub struct GlLoadError {
+    pub missing: Vec<&'static str>,
+}
+
+impl std::fmt::Display for GlLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "the OpenGL driver lacks {}", self.missing.join(", "))
+    }
 }
 
+impl std::error::Error for GlLoadError {}
+
+#[cold]
+fn not_loaded(name: &str) -> ! {
+    panic!("GL: {} is not available on this context, check `Features` first", name)
+}
+
+mod debug;
+
+pub use debug::{set_debug_hook, DebugHook};
+pub(crate) use debug::enable as enable_debug;
+
 gl_loader!(
-    fn glGetStringi(name: GLenum, index: GLuint) -> *const GLubyte,
+    fn glGetError() -> GLenum,
     fn glGetString(name: GLenum) -> *const GLubyte,
     fn glBindFramebuffer(target: GLenum, framebuffer: GLuint) -> (),
+    fn glGenFramebuffers(n: GLsizei, framebuffers: *mut GLuint) -> (),
+    fn glDeleteFramebuffers(n: GLsizei, framebuffers: *const GLuint) -> (),
+    fn glBlendFuncSeparate(
+        sfactorRGB: GLenum,
+        dfactorRGB: GLenum,
+        sfactorAlpha: GLenum,
+        dfactorAlpha: GLenum
+    ) -> (),
+    fn glBlendEquationSeparate(modeRGB: GLenum, modeAlpha: GLenum) -> (),
+    fn glDepthFunc(func: GLenum) -> (),
+    fn glDepthMask(flag: GLboolean) -> (),
+    fn glCullFace(mode: GLenum) -> (),
+    fn glColorMask(red: GLboolean, green: GLboolean, blue: GLboolean, alpha: GLboolean) -> (),
+    fn glDeleteBuffers(n: GLsizei, buffers: *const GLuint) -> (),
+    fn glDeleteTextures(n: GLsizei, textures: *const GLuint) -> (),
+    fn glDeleteProgram(program: GLuint) -> (),
+    fn glDeleteShader(shader: GLuint) -> (),
+    fn glDetachShader(program: GLuint, shader: GLuint) -> (),
+    fn glFramebufferTexture2D(
+        target: GLenum,
+        attachment: GLenum,
+        textarget: GLenum,
+        texture: GLuint,
+        level: GLint
+    ) -> (),
+    fn glCheckFramebufferStatus(target: GLenum) -> GLenum,
+    fn glUniform1fv(location: GLint, count: GLsizei, value: *const GLfloat) -> (),
     fn glUniform2fv(location: GLint, count: GLsizei, value: *const GLfloat) -> (),
+    fn glUniform3fv(location: GLint, count: GLsizei, value: *const GLfloat) -> (),
+    fn glUniform4fv(location: GLint, count: GLsizei, value: *const GLfloat) -> (),
+    fn glUniform1iv(location: GLint, count: GLsizei, value: *const GLint) -> (),
+    fn glUniform2iv(location: GLint, count: GLsizei, value: *const GLint) -> (),
+    fn glUniform3iv(location: GLint, count: GLsizei, value: *const GLint) -> (),
+    fn glUniform4iv(location: GLint, count: GLsizei, value: *const GLint) -> (),
+    fn glUniformMatrix4fv(
+        location: GLint,
+        count: GLsizei,
+        transpose: GLboolean,
+        value: *const GLfloat
+    ) -> (),
     fn glUniform1i(location: GLint, v0: GLint) -> (),
     fn glUseProgram(program: GLuint) -> (),
     fn glShaderSource(
@@ -216,32 +411,23 @@ gl_loader!(
         bufSize: GLsizei,
         length: *mut GLsizei,
         infoLog: *mut GLchar
-    ) -> (),    
-    fn glGetAttribLocation(program: GLuint, name: *const GLchar) -> GLint,    
+    ) -> (),
+    fn glGetAttribLocation(program: GLuint, name: *const GLchar) -> GLint,
     fn glDisableVertexAttribArray(index: GLuint) -> (),
     fn glCompileShader(shader: GLuint) -> (),
-    fn glVertexAttribDivisor(index: GLuint, divisor: GLuint) -> (),
     fn glBufferSubData(
         target: GLenum,
         offset: GLintptr,
         size: GLsizeiptr,
         data: *const ::std::os::raw::c_void
-    ) -> (),    
+    ) -> (),
     fn glGenBuffers(n: GLsizei, buffers: *mut GLuint) -> (),
-    fn glActiveTexture(texture: GLenum) -> (),    
+    fn glActiveTexture(texture: GLenum) -> (),
     fn glGetProgramiv(program: GLuint, pname: GLenum, params: *mut GLint) -> (),
     fn glBindTexture(target: GLenum, texture: GLuint) -> (),
     fn glCreateShader(type_: GLenum) -> GLuint,
-    fn glClearDepthf(d: GLfloat) -> (),
-    fn glCreateProgram() -> GLuint,    
+    fn glCreateProgram() -> GLuint,
     fn glViewport(x: GLint, y: GLint, width: GLsizei, height: GLsizei) -> (),
-    fn glDrawElementsInstanced(
-        mode: GLenum,
-        count: GLsizei,
-        type_: GLenum,
-        indices: *const ::std::os::raw::c_void,
-        instancecount: GLsizei
-    ) -> (),    
     fn glVertexAttribPointer(
         index: GLuint,
         size: GLint,
@@ -252,14 +438,13 @@ gl_loader!(
     ) -> (),
     fn glDisable(cap: GLenum) -> (),
     fn glBindBuffer(target: GLenum, buffer: GLuint) -> (),
-    fn glBindVertexArray(array: GLuint) -> (),
-    fn glScissor(x: GLint, y: GLint, width: GLsizei, height: GLsizei) -> (),    
+    fn glScissor(x: GLint, y: GLint, width: GLsizei, height: GLsizei) -> (),
     fn glBufferData(
         target: GLenum,
         size: GLsizeiptr,
         data: *const ::std::os::raw::c_void,
         usage: GLenum
-    ) -> (),    
+    ) -> (),
     fn glTexParameteri(target: GLenum, pname: GLenum, param: GLint) -> (),
     fn glGetIntegerv(pname: GLenum, params: *mut GLint) -> (),
     fn glEnable(cap: GLenum) -> (),
@@ -284,18 +469,55 @@ gl_loader!(
         type_: GLenum,
         pixels: *const GLvoid
     ) -> (),
-    fn glGenVertexArrays(n: GLsizei, arrays: *mut GLuint) -> (),
     fn glFrontFace(mode: GLenum) -> (),
-    fn glGenTextures(n: GLsizei, textures: *mut GLuint) -> ()
+    fn glGenTextures(n: GLsizei, textures: *mut GLuint) -> (),
+    fn glGenerateMipmap(target: GLenum) -> (),
+    fn glTexSubImage2D(
+        target: GLenum,
+        level: GLint,
+        xoffset: GLint,
+        yoffset: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        format: GLenum,
+        type_: GLenum,
+        pixels: *const GLvoid
+    ) -> (),
+    fn glReadPixels(
+        x: GLint,
+        y: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        format: GLenum,
+        type_: GLenum,
+        pixels: *mut GLvoid
+    ) -> (),
+    fn glDrawElements(
+        mode: GLenum,
+        count: GLsizei,
+        type_: GLenum,
+        indices: *const ::std::os::raw::c_void
+    ) -> ();
+    // GL 3 / GLES 3 and extensions, check `Features` or `loaded` before calling them
+    optional:
+    fn glGetStringi(name: GLenum, index: GLuint) -> *const GLubyte,
+    fn glVertexAttribDivisor(index: GLuint, divisor: GLuint) -> (),
+    fn glClearDepthf(d: GLfloat) -> (),
+    // desktop GL before 4.1 only has the double version
+    fn glClearDepth(d: f64) -> (),
+    fn glDrawElementsInstanced(
+        mode: GLenum,
+        count: GLsizei,
+        type_: GLenum,
+        indices: *const ::std::os::raw::c_void,
+        instancecount: GLsizei
+    ) -> (),
+    fn glBindVertexArray(array: GLuint) -> (),
+    fn glGenVertexArrays(n: GLsizei, arrays: *mut GLuint) -> (),
+    fn glGenQueries(n: GLsizei, ids: *mut GLuint) -> (),
+    fn glDeleteQueries(n: GLsizei, ids: *const GLuint) -> (),
+    fn glBeginQuery(target: GLenum, id: GLuint) -> (),
+    fn glEndQuery(target: GLenum) -> (),
+    fn glGetQueryObjectiv(id: GLuint, pname: GLenum, params: *mut GLint) -> (),
+    fn glGetQueryObjectui64v(id: GLuint, pname: GLenum, params: *mut GLuint64) -> ()
 );
-
-// note that glGetString only works after first glSwapBuffer,
-// not just after context creation
-pub unsafe fn is_gl2() -> bool {
-    let version_string = glGetString(super::gl::GL_VERSION);
-    let version_string = std::ffi::CStr::from_ptr(version_string as _)
-        .to_str()
-        .unwrap();
-
-    version_string.starts_with("2") || version_string.starts_with("OpenGL ES 2")
-}
@@ -0,0 +1,153 @@
+//! Opt-in GL error reporting, see `Conf::gl_debug`.
+//!
+//! With `KHR_debug` the driver calls back for every message it has, otherwise every
+//! `gl_loader!` function checks `glGetError` after the call.
+
+use std::{
+    ffi::CStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use super::*;
+
+/// Receives every GL debug message. Called on the thread that made the failing call,
+/// so it must not call GL itself.
+pub type DebugHook = Box<dyn FnMut(&str) + Send>;
+
+static HOOK: Mutex<Option<DebugHook>> = Mutex::new(None);
+static CHECK_ERRORS: AtomicBool = AtomicBool::new(false);
+
+type DebugProc = extern "system" fn(
+    source: GLenum,
+    type_: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    user_param: *mut GLvoid,
+);
+type DebugMessageCallback = extern "system" fn(callback: DebugProc, user_param: *const GLvoid);
+type DebugMessageControl = extern "system" fn(
+    source: GLenum,
+    type_: GLenum,
+    severity: GLenum,
+    count: GLsizei,
+    ids: *const GLuint,
+    enabled: GLboolean,
+);
+
+/// Replace the default hook, which prints to stderr
+pub fn set_debug_hook(hook: DebugHook) {
+    *HOOK.lock().unwrap_or_else(|e| e.into_inner()) = Some(hook);
+}
+
+pub(crate) fn report(message: &str) {
+    let mut hook = HOOK.lock().unwrap_or_else(|e| e.into_inner());
+    match hook.as_mut() {
+        Some(hook) => hook(message),
+        None => eprintln!("winopengl: {}", message),
+    }
+}
+
+/// Turn debug output on for the current context, `debug_output` as in `Features`.
+pub(crate) unsafe fn enable<T: FnMut(&str) -> Option<unsafe extern "C" fn()>>(
+    debug_output: bool,
+    mut getprocaddr: T,
+) {
+    let mut load = |name: &str| {
+        // the extension entry points carry the KHR suffix on GLES only
+        getprocaddr(name).or_else(|| getprocaddr(&format!("{}KHR", name)))
+    };
+    let callback = load("glDebugMessageCallback");
+    let control = load("glDebugMessageControl");
+
+    match (callback, control) {
+        (Some(callback), Some(control)) if debug_output => {
+            let callback: DebugMessageCallback = std::mem::transmute(callback);
+            let control: DebugMessageControl = std::mem::transmute(control);
+            glEnable(GL_DEBUG_OUTPUT);
+            // messages arrive inside the failing call instead of from a driver thread
+            glEnable(GL_DEBUG_OUTPUT_SYNCHRONOUS);
+            control(GL_DONT_CARE, GL_DONT_CARE, GL_DEBUG_SEVERITY_NOTIFICATION, 0, std::ptr::null(), GL_FALSE as _);
+            callback(debug_callback, std::ptr::null());
+            report("GL debug output through KHR_debug");
+        }
+        _ => {
+            // errors from before this point are not the next caller's
+            while glGetError() != GL_NO_ERROR {}
+            CHECK_ERRORS.store(true, Ordering::Relaxed);
+            report("GL debug output through glGetError after every call");
+        }
+    }
+}
+
+pub(crate) fn checking_errors() -> bool {
+    CHECK_ERRORS.load(Ordering::Relaxed)
+}
+
+/// Called by the `gl_loader!` functions after `name` returned, `args` only runs on error
+pub(crate) unsafe fn check_error(name: &str, args: impl FnOnce() -> String) {
+    let Some(get_error) = __pfns::glGetError else {
+        return;
+    };
+    let mut error = get_error();
+    if error == GL_NO_ERROR {
+        return;
+    }
+    let args = args();
+    // several errors can be pending, each glGetError returns one
+    while error != GL_NO_ERROR {
+        report(&format!("{}({}): {}", name, args, error_name(error)));
+        error = get_error();
+    }
+}
+
+fn error_name(error: GLenum) -> String {
+    match error {
+        GL_INVALID_ENUM => "GL_INVALID_ENUM".to_owned(),
+        GL_INVALID_VALUE => "GL_INVALID_VALUE".to_owned(),
+        GL_INVALID_OPERATION => "GL_INVALID_OPERATION".to_owned(),
+        GL_STACK_OVERFLOW => "GL_STACK_OVERFLOW".to_owned(),
+        GL_STACK_UNDERFLOW => "GL_STACK_UNDERFLOW".to_owned(),
+        GL_OUT_OF_MEMORY => "GL_OUT_OF_MEMORY".to_owned(),
+        GL_INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION".to_owned(),
+        _ => format!("error 0x{:x}", error),
+    }
+}
+
+extern "system" fn debug_callback(
+    _source: GLenum,
+    type_: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut GLvoid,
+) {
+    let message = if message.is_null() {
+        String::new()
+    } else if length >= 0 {
+        let bytes = unsafe { std::slice::from_raw_parts(message as *const u8, length as usize) };
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+    };
+    let type_ = match type_ {
+        GL_DEBUG_TYPE_ERROR => "error",
+        GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated",
+        GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        GL_DEBUG_TYPE_PORTABILITY => "portability",
+        GL_DEBUG_TYPE_PERFORMANCE => "performance",
+        _ => "other",
+    };
+    let severity = match severity {
+        GL_DEBUG_SEVERITY_HIGH => "high",
+        GL_DEBUG_SEVERITY_MEDIUM => "medium",
+        GL_DEBUG_SEVERITY_LOW => "low",
+        _ => "notification",
+    };
+    report(&format!("{} ({}, id {}): {}", type_, severity, id, message.trim_end()));
+}
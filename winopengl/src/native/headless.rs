@@ -0,0 +1,357 @@
+use crate::{
+    conf::{Conf, GlProfile, LoopMode},
+    native::{egl, pacing::FramePacer, NativeDisplayData},
+    Error, GraphicsContext, EventHandler,
+};
+
+use egl::LibEgl;
+
+/// Offscreen display: an EGL pbuffer the size of the configured window, no window system involved.
+pub(crate) struct Display {
+    display_data: NativeDisplayData,
+    libegl: LibEgl,
+    egl_display: egl::EGLDisplay,
+    surface: egl::EGLSurface,
+}
+
+impl crate::native::NativeDisplay for Display {
+    fn order_quit(&mut self) {
+        self.display_data.quit_ordered = true;
+    }
+
+    fn set_title(&mut self, _title: String) {}
+
+    fn set_fullscreen(&mut self, _fullscreen: bool) {}
+
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Display {
+    unsafe fn get_proc_address(&mut self, proc: &str) -> Option<unsafe extern "C" fn() -> ()> {
+        let cproc = std::ffi::CString::new(proc).unwrap();
+        (self.libegl.eglGetProcAddress.unwrap())(cproc.as_ptr())
+    }
+}
+
+/// Run the event handler for `frames` frames, or until it orders quit.
+pub fn run<F>(conf: &Conf, frames: u32, f: F) -> Result<(), Error>
+where
+    F: 'static + FnOnce(&mut GraphicsContext) -> Result<Box<dyn EventHandler>, Error>,
+{
+    unsafe {
+        let mut libegl =
+            LibEgl::try_load().ok_or_else(|| Error::Context("failed to load libEGL.so".to_string()))?;
+
+        let egl_display = egl::get_surfaceless_display(&mut libegl)
+            .map_err(|e| Error::Context(format!("EGL: failed to get display: {:?}", e)))?;
+        // EGL defaults to the GLES API, desktop profiles are not available here
+        let es_version = if conf.gl_profile == GlProfile::Es { conf.gl_version } else { (2, 0) };
+        let (gl_ctx, config, fell_back) = egl::create_egl_context(
+            &mut libegl,
+            egl_display,
+            true,
+            egl::EGL_PBUFFER_BIT,
+            conf.sample_count,
+            es_version,
+            conf.gl_debug,
+        )
+        .map_err(|e| match e {
+            egl::EglError::NoConfig => Error::PixelFormat("EGL: no config with 8 bit RGBA".to_string()),
+            e => Error::Context(format!("EGL: failed to create context: {:?}", e)),
+        })?;
+        let fell_back = fell_back
+            || conf.gl_profile != GlProfile::Es
+                && (conf.gl_version, conf.gl_profile) != ((2, 1), GlProfile::Compatibility);
+        let surface = egl::create_pbuffer_surface(
+            &mut libegl,
+            egl_display,
+            config,
+            conf.window_width,
+            conf.window_height,
+        )
+        .map_err(|e| Error::Window(format!("EGL: failed to create pbuffer: {:?}", e)))?;
+
+        if (libegl.eglMakeCurrent.unwrap())(egl_display, surface, surface, gl_ctx) == 0 {
+            return Err(Error::Context("EGL: failed to make context current".to_string()));
+        }
+
+        let mut display = Display {
+            display_data: Default::default(),
+            libegl,
+            egl_display,
+            surface,
+        };
+
+        // a pbuffer is never presented, there is no vblank to wait for
+        let size = (conf.window_width, conf.window_height);
+        let mut context =
+            super::new_graphics_context(conf, size, None, fell_back, |proc| display.get_proc_address(proc))?;
+
+        let mut event_handler = f(context.with_display(&mut display))?;
+        let mut pacer = FramePacer::new(LoopMode::Continuous);
+
+        for _ in 0..frames {
+            if display.display_data.quit_ordered {
+                break;
+            }
+            let dt = pacer.begin_frame(&mut context);
+            event_handler.update(context.with_display(&mut display), dt);
+            event_handler.draw(context.with_display(&mut display));
+            (display.libegl.eglSwapBuffers.unwrap())(display.egl_display, display.surface);
+            pacer.end_frame(&mut context);
+        }
+        drop(event_handler);
+        context.report_leaks();
+
+        let libegl = &mut display.libegl;
+        (libegl.eglMakeCurrent.unwrap())(
+            egl_display,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+        (libegl.eglDestroySurface.unwrap())(egl_display, surface);
+        (libegl.eglDestroyContext.unwrap())(egl_display, gl_ctx);
+        (libegl.eglTerminate.unwrap())(egl_display);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{
+        conf::{Conf, GlProfile},
+        graphics::{PassAction, RenderPass, Texture, TextureParams},
+        Error, EventHandler, GraphicsContext,
+    };
+
+    /// False when there is no headless context and `WINOPENGL_SKIP_GL_TESTS` is set,
+    /// panics on any other error so a machine without EGL cannot pass silently
+    pub(crate) fn started(result: Result<(), Error>) -> bool {
+        match result {
+            Err(Error::Context(e)) if std::env::var_os("WINOPENGL_SKIP_GL_TESTS").is_some() => {
+                eprintln!("skipped, no headless context: {}", e);
+                false
+            }
+            Err(e) => panic!("{} (set WINOPENGL_SKIP_GL_TESTS to skip the GL tests)", e),
+            Ok(()) => true,
+        }
+    }
+
+    struct Clear {
+        frames: Rc<RefCell<Vec<Vec<u8>>>>,
+        quit_after: usize,
+    }
+
+    impl EventHandler for Clear {
+        fn update(&mut self, _ctx: &mut GraphicsContext, _dt: f32) {}
+
+        fn draw(&mut self, ctx: &mut GraphicsContext) {
+            let frame = self.frames.borrow().len();
+            // a different red each frame, to tell them apart in the read back
+            ctx.begin_default_pass(PassAction::clear_color(frame as f32 / 255., 0.5, 1., 1.));
+            ctx.end_render_pass();
+            ctx.commit_frame();
+            self.frames.borrow_mut().push(ctx.read_pixels());
+            if self.frames.borrow().len() == self.quit_after {
+                ctx.order_quit();
+            }
+        }
+    }
+
+    /// Frames drawn by `start`, None when the machine has no usable EGL
+    fn run(frames: u32, quit_after: usize) -> Option<Vec<Vec<u8>>> {
+        let drawn = Rc::new(RefCell::new(Vec::new()));
+        let handler_frames = drawn.clone();
+        let conf = Conf {
+            window_width: 8,
+            window_height: 4,
+            headless_frames: Some(frames),
+            ..Default::default()
+        };
+        if !started(crate::start(conf, move |_| Ok(Box::new(Clear { frames: handler_frames, quit_after })))) {
+            return None;
+        }
+        let drawn = drawn.borrow().clone();
+        Some(drawn)
+    }
+
+    #[test]
+    fn draws_the_asked_frames_and_reads_them_back() {
+        let Some(frames) = run(3, 0) else { return };
+        assert_eq!(frames.len(), 3);
+        for (i, pixels) in frames.iter().enumerate() {
+            assert_eq!(pixels.len(), 8 * 4 * 4);
+            for p in pixels.chunks_exact(4) {
+                assert_eq!(p[0], i as u8);
+                assert!(p[1].abs_diff(128) <= 1);
+                assert_eq!(&p[2..], &[255, 255]);
+            }
+        }
+    }
+
+    /// Frame n begins n render passes
+    struct Passes {
+        frame: u32,
+    }
+
+    impl EventHandler for Passes {
+        fn update(&mut self, _ctx: &mut GraphicsContext, _dt: f32) {}
+
+        fn draw(&mut self, ctx: &mut GraphicsContext) {
+            self.frame += 1;
+            for _ in 0..self.frame {
+                ctx.begin_default_pass(PassAction::Nothing);
+                ctx.end_render_pass();
+            }
+            ctx.commit_frame();
+        }
+    }
+
+    #[test]
+    fn trace_lines_match_their_frame() {
+        let path = std::env::temp_dir().join("winopengl_trace_test.csv");
+        let trace = path.clone();
+        let conf = Conf {
+            window_width: 8,
+            window_height: 4,
+            headless_frames: Some(3),
+            ..Default::default()
+        };
+        let result = crate::start(conf, move |ctx| {
+            ctx.start_trace(&trace).unwrap();
+            Ok(Box::new(Passes { frame: 0 }))
+        });
+        if !started(result) {
+            return;
+        }
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let lines: Vec<Vec<&str>> = csv.lines().map(|l| l.split(',').collect()).collect();
+        let passes = lines[0].iter().position(|c| *c == "passes").unwrap();
+        assert_eq!(lines.len(), 4);
+        for (n, line) in lines.iter().enumerate().skip(1) {
+            assert_eq!(line[0], n.to_string());
+            assert_eq!(line[passes], n.to_string());
+        }
+    }
+
+    #[test]
+    fn creates_the_asked_gles_version_or_falls_back_to_2() {
+        let info = Rc::new(RefCell::new(None));
+        let captured = info.clone();
+        let conf = Conf {
+            window_width: 8,
+            window_height: 4,
+            gl_profile: GlProfile::Es,
+            gl_version: (3, 0),
+            headless_frames: Some(0),
+            ..Default::default()
+        };
+        let result = crate::start(conf, move |ctx| {
+            *captured.borrow_mut() = Some(ctx.context_info().clone());
+            Ok(Box::new(Clear { frames: Default::default(), quit_after: 0 }))
+        });
+        if !started(result) {
+            return;
+        }
+        let info = info.borrow_mut().take().unwrap();
+        assert_eq!(info.profile, GlProfile::Es);
+        if info.fell_back {
+            assert!(info.version >= (2, 0), "{}", info.version_string);
+        } else {
+            assert!(info.version >= (3, 0), "{}", info.version_string);
+        }
+    }
+
+    #[test]
+    fn creates_a_debug_context_for_gl_debug() {
+        let flags = Rc::new(RefCell::new(None));
+        let captured = flags.clone();
+        let conf = Conf {
+            window_width: 8,
+            window_height: 4,
+            gl_profile: GlProfile::Es,
+            gl_version: (3, 2),
+            gl_debug: true,
+            headless_frames: Some(0),
+            ..Default::default()
+        };
+        let result = crate::start(conf, move |ctx| {
+            // GL_CONTEXT_FLAGS only exists from GLES 3.2
+            if ctx.context_info().version >= (3, 2) {
+                let mut context_flags = 0;
+                unsafe { crate::gl::glGetIntegerv(crate::gl::GL_CONTEXT_FLAGS, &mut context_flags) };
+                *captured.borrow_mut() = Some(context_flags as u32);
+            }
+            Ok(Box::new(Clear { frames: Default::default(), quit_after: 0 }))
+        });
+        if !started(result) {
+            return;
+        }
+        let flags = flags.borrow_mut().take();
+        if let Some(flags) = flags {
+            assert_ne!(flags & crate::gl::GL_CONTEXT_FLAG_DEBUG_BIT, 0);
+        }
+    }
+
+    /// Reads the pixels of a pass smaller than the window
+    struct ReadPass {
+        pass: RenderPass,
+        pixels: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl EventHandler for ReadPass {
+        fn update(&mut self, _ctx: &mut GraphicsContext, _dt: f32) {}
+
+        fn draw(&mut self, ctx: &mut GraphicsContext) {
+            ctx.begin_pass(Some(self.pass), PassAction::clear_color(1., 0., 0., 1.));
+            *self.pixels.borrow_mut() = ctx.read_pixels();
+            ctx.end_render_pass();
+            ctx.commit_frame();
+        }
+    }
+
+    #[test]
+    fn reads_the_bound_pass_at_its_size() {
+        let pixels = Rc::new(RefCell::new(Vec::new()));
+        let captured = pixels.clone();
+        let conf = Conf {
+            window_width: 8,
+            window_height: 4,
+            headless_frames: Some(1),
+            ..Default::default()
+        };
+        let result = crate::start(conf, move |ctx| {
+            let texture = Texture::new_render_texture(
+                ctx,
+                TextureParams {
+                    width: 2,
+                    height: 3,
+                    ..Default::default()
+                },
+            );
+            let pass = RenderPass::new(ctx, texture, None)?;
+            Ok(Box::new(ReadPass { pass, pixels: captured }))
+        });
+        if !started(result) {
+            return;
+        }
+        let pixels = pixels.borrow();
+        assert_eq!(pixels.len(), 2 * 3 * 4);
+        for p in pixels.chunks_exact(4) {
+            assert_eq!(p, &[255, 0, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn stops_when_quit_is_ordered() {
+        let Some(frames) = run(10, 2) else { return };
+        assert_eq!(frames.len(), 2);
+    }
+}
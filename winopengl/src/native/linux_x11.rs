@@ -0,0 +1,324 @@
+use std::ffi::{CStr, CString};
+
+use crate::{
+    conf::Conf,
+    native::{pacing::{self, FramePacer}, NativeDisplayData},
+    Error, GraphicsContext, EventHandler,
+};
+
+mod glx;
+mod keycodes;
+mod libx11;
+
+use glx::LibGlx;
+use libx11::LibX11;
+
+pub(crate) struct Display {
+    display_data: NativeDisplayData,
+    libx11: LibX11,
+    libglx: LibGlx,
+    x_display: *mut libx11::Display,
+    window: libx11::Window,
+    colormap: libx11::Colormap,
+    wm_delete_window: libx11::Atom,
+}
+
+impl crate::native::NativeDisplay for Display {
+    fn order_quit(&mut self) {
+        self.display_data.quit_ordered = true;
+    }
+
+    fn set_title(&mut self, title: String) {
+        // XStoreName takes a C string, drop the NULs instead of cutting the title
+        let title = CString::new(title.replace('\0', "")).unwrap_or_default();
+
+        unsafe {
+            (self.libx11.XStoreName)(self.x_display, self.window, title.as_ptr());
+            (self.libx11.XFlush)(self.x_display);
+        }
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        unsafe {
+            // EWMH: ask the window manager through the root window
+            let wm_state = CString::new("_NET_WM_STATE").unwrap();
+            let wm_fullscreen = CString::new("_NET_WM_STATE_FULLSCREEN").unwrap();
+            let wm_state = (self.libx11.XInternAtom)(self.x_display, wm_state.as_ptr(), libx11::False);
+            let wm_fullscreen =
+                (self.libx11.XInternAtom)(self.x_display, wm_fullscreen.as_ptr(), libx11::False);
+
+            let mut event: libx11::XEvent = std::mem::zeroed();
+            event.xclient = libx11::XClientMessageEvent {
+                type_: libx11::ClientMessage,
+                serial: 0,
+                send_event: libx11::True,
+                display: self.x_display,
+                window: self.window,
+                message_type: wm_state,
+                format: 32,
+                data: [
+                    if fullscreen {
+                        libx11::_NET_WM_STATE_ADD
+                    } else {
+                        libx11::_NET_WM_STATE_REMOVE
+                    },
+                    wm_fullscreen as _,
+                    0,
+                    1,
+                    0,
+                ],
+            };
+            let screen = (self.libx11.XDefaultScreen)(self.x_display);
+            let root = (self.libx11.XRootWindow)(self.x_display, screen);
+            (self.libx11.XSendEvent)(
+                self.x_display,
+                root,
+                libx11::False,
+                libx11::SubstructureRedirectMask | libx11::SubstructureNotifyMask,
+                &mut event,
+            );
+            (self.libx11.XFlush)(self.x_display);
+        }
+    }
+
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Display {
+    /// `Xft.dpi` from the X resources, the setting desktops use for UI scaling
+    unsafe fn dpi_scale(&mut self) -> f32 {
+        let resources = (self.libx11.XResourceManagerString)(self.x_display);
+        if resources.is_null() {
+            return 1.;
+        }
+        CStr::from_ptr(resources)
+            .to_string_lossy()
+            .lines()
+            .find_map(|line| line.strip_prefix("Xft.dpi:"))
+            .and_then(|dpi| dpi.trim().parse::<f32>().ok())
+            .map_or(1., |dpi| dpi / 96.)
+    }
+
+    unsafe fn get_proc_address(&mut self, proc: &str) -> Option<unsafe extern "C" fn() -> ()> {
+        let cproc = CString::new(proc).unwrap();
+        (self.libglx.glXGetProcAddressARB)(cproc.as_ptr())
+            .or_else(|| self.libglx.module.get_symbol(proc).ok())
+    }
+
+    unsafe fn create_window(
+        &mut self,
+        visual: &libx11::XVisualInfo,
+        window_title: &str,
+        width: i32,
+        height: i32,
+        resizable: bool,
+    ) -> Result<(), Error> {
+        let screen = (self.libx11.XDefaultScreen)(self.x_display);
+        let root = (self.libx11.XRootWindow)(self.x_display, screen);
+        self.colormap =
+            (self.libx11.XCreateColormap)(self.x_display, root, visual.visual, libx11::AllocNone);
+
+        let mut wa: libx11::XSetWindowAttributes = std::mem::zeroed();
+        wa.colormap = self.colormap;
+        wa.border_pixel = 0;
+        wa.event_mask = libx11::StructureNotifyMask
+            | libx11::KeyPressMask
+            | libx11::KeyReleaseMask
+            | libx11::PointerMotionMask
+            | libx11::ExposureMask
+            | libx11::FocusChangeMask;
+
+        self.window = (self.libx11.XCreateWindow)(
+            self.x_display,
+            root,
+            0,
+            0,
+            width as _,
+            height as _,
+            0,
+            visual.depth,
+            libx11::InputOutput,
+            visual.visual,
+            libx11::CWBorderPixel | libx11::CWColormap | libx11::CWEventMask,
+            &mut wa,
+        );
+        if self.window == 0 {
+            return Err(Error::Window("X11: XCreateWindow failed".to_string()));
+        }
+
+        let wm_delete_window = CString::new("WM_DELETE_WINDOW").unwrap();
+        self.wm_delete_window =
+            (self.libx11.XInternAtom)(self.x_display, wm_delete_window.as_ptr(), libx11::False);
+        (self.libx11.XSetWMProtocols)(
+            self.x_display,
+            self.window,
+            &mut self.wm_delete_window,
+            1,
+        );
+
+        if !resizable {
+            let mut hints: libx11::XSizeHints = std::mem::zeroed();
+            hints.flags = libx11::PMinSize | libx11::PMaxSize;
+            hints.min_width = width;
+            hints.max_width = width;
+            hints.min_height = height;
+            hints.max_height = height;
+            (self.libx11.XSetWMNormalHints)(self.x_display, self.window, &mut hints);
+        }
+
+        crate::native::NativeDisplay::set_title(self, window_title.to_string());
+        (self.libx11.XMapWindow)(self.x_display, self.window);
+        (self.libx11.XFlush)(self.x_display);
+        Ok(())
+    }
+
+    unsafe fn process_event(
+        &mut self,
+        event: &mut libx11::XEvent,
+        context: &mut GraphicsContext,
+        event_handler: &mut dyn EventHandler,
+    ) {
+        match event.type_ {
+            libx11::KeyPress => {
+                let keysym = (self.libx11.XLookupKeysym)(&mut event.xkey, 0);
+                let keycode = keycodes::translate_keysym(keysym);
+                event_handler.key_down_event(context.with_display(self), keycode);
+
+                // Latin-1 text of the key, enough without an input method
+                let mut text = [0u8; 32];
+                let len = (self.libx11.XLookupString)(
+                    &mut event.xkey,
+                    text.as_mut_ptr() as _,
+                    text.len() as _,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                );
+                for &byte in &text[..len.max(0) as usize] {
+                    let character = byte as char;
+                    if !character.is_control() {
+                        event_handler.char_event(context.with_display(self), character);
+                    }
+                }
+            }
+            libx11::KeyRelease => {
+                let keysym = (self.libx11.XLookupKeysym)(&mut event.xkey, 0);
+                let keycode = keycodes::translate_keysym(keysym);
+                event_handler.key_up_event(context.with_display(self), keycode);
+            }
+            libx11::MotionNotify => {
+                let (x, y) = (event.xmotion.x as f32, event.xmotion.y as f32);
+                event_handler.mouse_motion_event(context.with_display(self), x, y);
+            }
+            libx11::ConfigureNotify => {
+                let (width, height) = (event.xconfigure.width, event.xconfigure.height);
+                if (width, height) != context.screen_size() && width > 0 && height > 0 {
+                    context.resize(width, height);
+                    event_handler.resize_event(context.with_display(self), width, height);
+                }
+            }
+            libx11::ClientMessage if event.xclient.data[0] as libx11::Atom == self.wm_delete_window => {
+                self.display_data.quit_ordered = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Display {
+    /// Block until the X connection has an event or `timeout` passes, `None` waiting forever
+    unsafe fn wait_for_events(&mut self, timeout: Option<std::time::Duration>) {
+        if timeout == Some(std::time::Duration::ZERO) || (self.libx11.XPending)(self.x_display) > 0 {
+            return;
+        }
+        let mut fd = libc::pollfd {
+            fd: (self.libx11.XConnectionNumber)(self.x_display),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.map_or(-1, |timeout| pacing::timeout_millis(timeout).min(i32::MAX as u32) as i32);
+        libc::poll(&mut fd, 1, timeout);
+    }
+}
+
+pub fn run<F>(conf: &Conf, f: F) -> Result<(), Error>
+where
+    F: 'static + FnOnce(&mut GraphicsContext) -> Result<Box<dyn EventHandler>, Error>,
+{
+    unsafe {
+        let libx11 = LibX11::try_load().ok_or_else(|| Error::Window("failed to load libX11.so".to_string()))?;
+        let libglx = LibGlx::try_load().ok_or_else(|| Error::Context("failed to load libGL.so".to_string()))?;
+
+        (libx11.XInitThreads)();
+        let x_display = (libx11.XOpenDisplay)(std::ptr::null());
+        if x_display.is_null() {
+            return Err(Error::Window("X11: failed to open display, is $DISPLAY set?".to_string()));
+        }
+        // Auto-repeat then sends KeyPress events only, without the KeyRelease before each of them,
+        // so KeyRelease is only seen when the key is actually released
+        (libx11.XkbSetDetectableAutoRepeat)(x_display, libx11::True, std::ptr::null_mut());
+        let screen = (libx11.XDefaultScreen)(x_display);
+
+        let mut display = Display {
+            display_data: Default::default(),
+            libx11,
+            libglx,
+            x_display,
+            window: 0,
+            colormap: 0,
+            wm_delete_window: 0,
+        };
+
+        let dpi_scale = if conf.high_dpi { display.dpi_scale() } else { 1. };
+        let width = (conf.window_width as f32 * dpi_scale) as i32;
+        let height = (conf.window_height as f32 * dpi_scale) as i32;
+
+        let mut glx = glx::Glx::new(&mut display, screen, conf.sample_count)?;
+        display.create_window(
+            &glx.visual,
+            &conf.window_title,
+            width,
+            height,
+            conf.window_resizable,
+        )?;
+        if conf.fullscreen {
+            crate::native::NativeDisplay::set_fullscreen(&mut display, true);
+        }
+        let (gl_ctx, fell_back) = glx.create_context(&mut display, conf)?;
+        let swap_interval = glx.set_swap_interval(&mut display, conf.swap_interval);
+
+        // going fullscreen arrives later as a ConfigureNotify
+        let mut context = super::new_graphics_context(conf, (width, height), swap_interval, fell_back, |proc| {
+            display.get_proc_address(proc)
+        })?;
+        context.dpi_scale = dpi_scale;
+
+        let mut event_handler = f(context.with_display(&mut display))?;
+        let mut pacer = FramePacer::new(conf.loop_mode);
+
+        while !display.display_data.quit_ordered {
+            while (display.libx11.XPending)(display.x_display) > 0 {
+                let mut event: libx11::XEvent = std::mem::zeroed();
+                (display.libx11.XNextEvent)(display.x_display, &mut event);
+                display.process_event(&mut event, &mut context, &mut *event_handler);
+            }
+            let dt = pacer.begin_frame(&mut context);
+            event_handler.update(context.with_display(&mut display), dt);
+            event_handler.draw(context.with_display(&mut display));
+            (display.libglx.glXSwapBuffers)(display.x_display, display.window);
+            pacer.end_frame(&mut context);
+            pacer.sleep_until_next_frame();
+            display.wait_for_events(pacer.event_timeout(&context));
+        }
+        drop(event_handler);
+        context.report_leaks();
+
+        (display.libglx.glXMakeCurrent)(display.x_display, 0, std::ptr::null_mut());
+        (display.libglx.glXDestroyContext)(display.x_display, gl_ctx);
+        (display.libx11.XDestroyWindow)(display.x_display, display.window);
+        (display.libx11.XFreeColormap)(display.x_display, display.colormap);
+        (display.libx11.XCloseDisplay)(display.x_display);
+    }
+    Ok(())
+}
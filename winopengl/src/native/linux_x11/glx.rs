@@ -0,0 +1,313 @@
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals)]
+
+use std::os::raw::{c_char, c_int, c_uint};
+
+use super::{
+    libx11::{self, Bool, XVisualInfo},
+    Display,
+};
+use crate::{
+    conf::{Conf, GlProfile},
+    Error,
+};
+
+pub type GLXContext = *mut std::ffi::c_void;
+pub type GLXFBConfig = *mut std::ffi::c_void;
+pub type GLXDrawable = libx11::XID;
+
+pub const GLX_DOUBLEBUFFER: c_int = 5;
+pub const GLX_RED_SIZE: c_int = 8;
+pub const GLX_GREEN_SIZE: c_int = 9;
+pub const GLX_BLUE_SIZE: c_int = 10;
+pub const GLX_ALPHA_SIZE: c_int = 11;
+pub const GLX_DEPTH_SIZE: c_int = 12;
+pub const GLX_STENCIL_SIZE: c_int = 13;
+pub const GLX_SAMPLE_BUFFERS: c_int = 100000;
+pub const GLX_SAMPLES: c_int = 100001;
+pub const GLX_DRAWABLE_TYPE: c_int = 0x8010;
+pub const GLX_RENDER_TYPE: c_int = 0x8011;
+pub const GLX_X_RENDERABLE: c_int = 0x8012;
+pub const GLX_RGBA_TYPE: c_int = 0x8014;
+pub const GLX_WINDOW_BIT: c_int = 0x00000001;
+pub const GLX_RGBA_BIT: c_int = 0x00000001;
+pub const GLX_CONTEXT_MAJOR_VERSION_ARB: c_int = 0x2091;
+pub const GLX_CONTEXT_MINOR_VERSION_ARB: c_int = 0x2092;
+pub const GLX_CONTEXT_FLAGS_ARB: c_int = 0x2094;
+pub const GLX_CONTEXT_DEBUG_BIT_ARB: c_int = 0x00000001;
+pub const GLX_CONTEXT_PROFILE_MASK_ARB: c_int = 0x9126;
+pub const GLX_CONTEXT_CORE_PROFILE_BIT_ARB: c_int = 0x00000001;
+pub const GLX_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB: c_int = 0x00000002;
+pub const GLX_CONTEXT_ES2_PROFILE_BIT_EXT: c_int = 0x00000004;
+
+pub type glXGetProcAddressARB =
+    unsafe extern "C" fn(_: *const c_char) -> Option<unsafe extern "C" fn()>;
+pub type glXQueryExtension =
+    unsafe extern "C" fn(_: *mut libx11::Display, _: *mut c_int, _: *mut c_int) -> Bool;
+pub type glXQueryExtensionsString =
+    unsafe extern "C" fn(_: *mut libx11::Display, _: c_int) -> *const c_char;
+pub type glXChooseFBConfig = unsafe extern "C" fn(
+    _: *mut libx11::Display,
+    _: c_int,
+    _: *const c_int,
+    _: *mut c_int,
+) -> *mut GLXFBConfig;
+pub type glXGetVisualFromFBConfig =
+    unsafe extern "C" fn(_: *mut libx11::Display, _: GLXFBConfig) -> *mut XVisualInfo;
+pub type glXCreateNewContext = unsafe extern "C" fn(
+    _: *mut libx11::Display,
+    _: GLXFBConfig,
+    _: c_int,
+    _: GLXContext,
+    _: Bool,
+) -> GLXContext;
+pub type glXDestroyContext = unsafe extern "C" fn(_: *mut libx11::Display, _: GLXContext);
+pub type glXMakeCurrent =
+    unsafe extern "C" fn(_: *mut libx11::Display, _: GLXDrawable, _: GLXContext) -> Bool;
+pub type glXSwapBuffers = unsafe extern "C" fn(_: *mut libx11::Display, _: GLXDrawable);
+
+type CreateContextAttribsARB = unsafe extern "C" fn(
+    _: *mut libx11::Display,
+    _: GLXFBConfig,
+    _: GLXContext,
+    _: Bool,
+    _: *const c_int,
+) -> GLXContext;
+type SwapIntervalEXT = unsafe extern "C" fn(_: *mut libx11::Display, _: GLXDrawable, _: c_int);
+type SwapIntervalMESA = unsafe extern "C" fn(_: c_uint) -> c_int;
+
+pub struct LibGlx {
+    pub module: crate::native::module::Module,
+    pub glXGetProcAddressARB: glXGetProcAddressARB,
+    pub glXQueryExtension: glXQueryExtension,
+    pub glXQueryExtensionsString: glXQueryExtensionsString,
+    pub glXChooseFBConfig: glXChooseFBConfig,
+    pub glXGetVisualFromFBConfig: glXGetVisualFromFBConfig,
+    pub glXCreateNewContext: glXCreateNewContext,
+    pub glXDestroyContext: glXDestroyContext,
+    pub glXMakeCurrent: glXMakeCurrent,
+    pub glXSwapBuffers: glXSwapBuffers,
+}
+
+impl LibGlx {
+    pub fn try_load() -> Option<LibGlx> {
+        crate::native::module::Module::load("libGL.so.1")
+            .or_else(|_| crate::native::module::Module::load("libGL.so"))
+            .ok()
+            .and_then(|module| {
+                Some(LibGlx {
+                    glXGetProcAddressARB: module.get_symbol("glXGetProcAddressARB").ok()?,
+                    glXQueryExtension: module.get_symbol("glXQueryExtension").ok()?,
+                    glXQueryExtensionsString: module.get_symbol("glXQueryExtensionsString").ok()?,
+                    glXChooseFBConfig: module.get_symbol("glXChooseFBConfig").ok()?,
+                    glXGetVisualFromFBConfig: module.get_symbol("glXGetVisualFromFBConfig").ok()?,
+                    glXCreateNewContext: module.get_symbol("glXCreateNewContext").ok()?,
+                    glXDestroyContext: module.get_symbol("glXDestroyContext").ok()?,
+                    glXMakeCurrent: module.get_symbol("glXMakeCurrent").ok()?,
+                    glXSwapBuffers: module.get_symbol("glXSwapBuffers").ok()?,
+                    module,
+                })
+            })
+    }
+}
+
+unsafe fn get_glx_proc_address<T>(libglx: &mut LibGlx, proc: &str) -> Option<T> {
+    let proc = std::ffi::CString::new(proc).unwrap();
+    let proc = (libglx.glXGetProcAddressARB)(proc.as_ptr() as *const _);
+
+    proc.map(|proc| std::mem::transmute_copy(&proc))
+}
+
+pub struct Glx {
+    CreateContextAttribsARB: Option<CreateContextAttribsARB>,
+    SwapIntervalEXT: Option<SwapIntervalEXT>,
+    SwapIntervalMESA: Option<SwapIntervalMESA>,
+
+    arb_create_context: bool,
+    ext_create_context_es2_profile: bool,
+    ext_swap_control: bool,
+    mesa_swap_control: bool,
+
+    fbconfig: GLXFBConfig,
+    pub visual: XVisualInfo,
+}
+
+impl Glx {
+    pub(crate) unsafe fn new(display: &mut Display, screen: c_int, sample_count: i32) -> Result<Glx, Error> {
+        let mut error_base = 0;
+        let mut event_base = 0;
+        if (display.libglx.glXQueryExtension)(display.x_display, &mut error_base, &mut event_base)
+            == 0
+        {
+            return Err(Error::Context("GLX: GLX extension not found".to_string()));
+        }
+
+        let extensions = (display.libglx.glXQueryExtensionsString)(display.x_display, screen);
+        let extensions = if extensions.is_null() {
+            String::new()
+        } else {
+            std::ffi::CStr::from_ptr(extensions).to_string_lossy().into_owned()
+        };
+        let glx_ext_supported = |ext: &str| -> bool {
+            extensions.split_whitespace().any(|supported| supported == ext)
+        };
+
+        let arb_create_context = glx_ext_supported("GLX_ARB_create_context");
+        let ext_create_context_es2_profile = glx_ext_supported("GLX_EXT_create_context_es2_profile");
+        let ext_swap_control = glx_ext_supported("GLX_EXT_swap_control");
+        let mesa_swap_control = glx_ext_supported("GLX_MESA_swap_control");
+
+        let CreateContextAttribsARB: Option<CreateContextAttribsARB> =
+            get_glx_proc_address(&mut display.libglx, "glXCreateContextAttribsARB");
+        let SwapIntervalEXT: Option<SwapIntervalEXT> =
+            get_glx_proc_address(&mut display.libglx, "glXSwapIntervalEXT");
+        let SwapIntervalMESA: Option<SwapIntervalMESA> =
+            get_glx_proc_address(&mut display.libglx, "glXSwapIntervalMESA");
+
+        #[rustfmt::skip]
+        let mut attributes = vec![
+            GLX_X_RENDERABLE, 1,
+            GLX_DRAWABLE_TYPE, GLX_WINDOW_BIT,
+            GLX_RENDER_TYPE, GLX_RGBA_BIT,
+            GLX_RED_SIZE, 8,
+            GLX_GREEN_SIZE, 8,
+            GLX_BLUE_SIZE, 8,
+            GLX_ALPHA_SIZE, 8,
+            GLX_DEPTH_SIZE, 24,
+            GLX_STENCIL_SIZE, 8,
+            GLX_DOUBLEBUFFER, 1,
+        ];
+        let choose = |attributes: &[c_int]| {
+            let attributes: Vec<c_int> = attributes.iter().copied().chain([0]).collect();
+            let mut count = 0;
+            let configs = (display.libglx.glXChooseFBConfig)(
+                display.x_display,
+                screen,
+                attributes.as_ptr(),
+                &mut count,
+            );
+            (configs, count)
+        };
+        let (mut configs, mut count) = (std::ptr::null_mut(), 0);
+        if sample_count > 1 {
+            let mut multisampled = attributes.clone();
+            multisampled.extend_from_slice(&[GLX_SAMPLE_BUFFERS, 1, GLX_SAMPLES, sample_count]);
+            (configs, count) = choose(&multisampled);
+        }
+        if configs.is_null() || count == 0 {
+            // no MSAA then
+            attributes.extend_from_slice(&[GLX_SAMPLE_BUFFERS, 0]);
+            (configs, count) = choose(&attributes);
+        }
+        if configs.is_null() || count == 0 {
+            return Err(Error::PixelFormat("GLX: no framebuffer config with 8 bit RGBA, depth and stencil".to_string()));
+        }
+        // glXChooseFBConfig already sorts the configs by how well they match
+        let fbconfig = *configs;
+        (display.libx11.XFree)(configs as *mut _);
+
+        let visual = (display.libglx.glXGetVisualFromFBConfig)(display.x_display, fbconfig);
+        if visual.is_null() {
+            return Err(Error::PixelFormat("GLX: no visual for the framebuffer config".to_string()));
+        }
+        let visual_info = *visual;
+        (display.libx11.XFree)(visual as *mut _);
+
+        Ok(Glx {
+            CreateContextAttribsARB,
+            SwapIntervalEXT,
+            SwapIntervalMESA,
+
+            arb_create_context,
+            ext_create_context_es2_profile,
+            ext_swap_control,
+            mesa_swap_control,
+
+            fbconfig,
+            visual: visual_info,
+        })
+    }
+
+    /// Context with the `Conf` version and profile, or the 2.1 / ES 2.0 fallback.
+    /// Also returns whether the fallback was used.
+    pub(crate) unsafe fn create_context(&mut self, display: &mut Display, conf: &Conf) -> Result<(GLXContext, bool), Error> {
+        // what was asked, then the versions every driver has
+        let mut attempts = vec![(conf.gl_version, conf.gl_profile)];
+        if conf.gl_profile == GlProfile::Es {
+            attempts.push(((2, 0), GlProfile::Es));
+        }
+        attempts.push(((2, 1), GlProfile::Compatibility));
+
+        // KHR_debug only reports everything on a debug context
+        let flags = if conf.gl_debug { GLX_CONTEXT_DEBUG_BIT_ARB } else { 0 };
+        let mut gl_ctx = std::ptr::null_mut();
+        let mut fell_back = false;
+        if let Some(create_context_attribs) = self.CreateContextAttribsARB.filter(|_| self.arb_create_context) {
+            // a refused version is reported as an X error, which would exit the process by default
+            let previous_handler = (display.libx11.XSetErrorHandler)(Some(ignore_x_error));
+            for (i, &((major, minor), profile)) in attempts.iter().enumerate() {
+                let profile_mask = match profile {
+                    GlProfile::Compatibility => GLX_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+                    GlProfile::Core => GLX_CONTEXT_CORE_PROFILE_BIT_ARB,
+                    GlProfile::Es if self.ext_create_context_es2_profile => GLX_CONTEXT_ES2_PROFILE_BIT_EXT,
+                    GlProfile::Es => continue,
+                };
+                #[rustfmt::skip]
+                let attrs = [
+                    GLX_CONTEXT_MAJOR_VERSION_ARB, major,
+                    GLX_CONTEXT_MINOR_VERSION_ARB, minor,
+                    GLX_CONTEXT_PROFILE_MASK_ARB, profile_mask,
+                    GLX_CONTEXT_FLAGS_ARB, flags,
+                    0, 0,
+                ];
+                gl_ctx = create_context_attribs(
+                    display.x_display,
+                    self.fbconfig,
+                    std::ptr::null_mut(),
+                    libx11::True,
+                    attrs.as_ptr(),
+                );
+                (display.libx11.XSync)(display.x_display, libx11::False);
+                if !gl_ctx.is_null() {
+                    fell_back = i > 0;
+                    break;
+                }
+            }
+            (display.libx11.XSetErrorHandler)(previous_handler);
+        }
+        if gl_ctx.is_null() {
+            // whatever the driver gives without GLX_ARB_create_context
+            fell_back = attempts[0] != ((2, 1), GlProfile::Compatibility);
+            gl_ctx = (display.libglx.glXCreateNewContext)(
+                display.x_display,
+                self.fbconfig,
+                GLX_RGBA_TYPE,
+                std::ptr::null_mut(),
+                libx11::True,
+            );
+        }
+        if gl_ctx.is_null() {
+            return Err(Error::Context("GLX: glXCreateNewContext failed".to_string()));
+        }
+        (display.libglx.glXMakeCurrent)(display.x_display, display.window, gl_ctx);
+
+        Ok((gl_ctx, fell_back))
+    }
+
+    /// Returns the interval set, `None` without a swap control extension
+    pub(crate) unsafe fn set_swap_interval(&mut self, display: &mut Display, swap_interval: i32) -> Option<i32> {
+        match (self.SwapIntervalEXT, self.SwapIntervalMESA) {
+            (Some(swap_interval_ext), _) if self.ext_swap_control => {
+                swap_interval_ext(display.x_display, display.window, swap_interval);
+                Some(swap_interval)
+            }
+            (_, Some(swap_interval_mesa)) if self.mesa_swap_control && swap_interval_mesa(swap_interval as _) == 0 => {
+                Some(swap_interval)
+            }
+            _ => None,
+        }
+    }
+}
+
+unsafe extern "C" fn ignore_x_error(_: *mut libx11::Display, _: *mut std::ffi::c_void) -> c_int {
+    0
+}
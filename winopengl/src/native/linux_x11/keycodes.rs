@@ -0,0 +1,26 @@
+use crate::event::KeyCode;
+
+use super::libx11::KeySym;
+
+pub fn translate_keysym(keysym: KeySym) -> KeyCode {
+    // same as GLFW
+    match keysym {
+        0x070 | 0x050 => KeyCode::P,
+        0xff1b => KeyCode::Escape,
+        0xff54 => KeyCode::Down,
+        0xff51 => KeyCode::Left,
+        0xff53 => KeyCode::Right,
+        0xff52 => KeyCode::Up,
+        0xffc0 => KeyCode::F3,
+        0xffc8 => KeyCode::F11,
+        0xffc9 => KeyCode::F12,
+        0xff55 => KeyCode::PageUp,
+        0xff56 => KeyCode::PageDown,
+        0xff0d => KeyCode::Enter,
+        0xff08 => KeyCode::Backspace,
+        0xff09 => KeyCode::Tab,
+        // twosuperior on AZERTY
+        0x060 | 0x0b2 => KeyCode::GraveAccent,
+        _ => KeyCode::Unknown,
+    }
+}
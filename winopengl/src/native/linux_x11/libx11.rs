@@ -0,0 +1,325 @@
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals)]
+
+use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong};
+
+#[allow(clippy::upper_case_acronyms)]
+pub type XID = c_ulong;
+pub type Window = XID;
+pub type Colormap = XID;
+pub type Cursor = XID;
+pub type Pixmap = XID;
+pub type KeySym = XID;
+pub type Atom = c_ulong;
+pub type VisualID = c_ulong;
+pub type Time = c_ulong;
+pub type Bool = c_int;
+pub type Status = c_int;
+
+#[repr(C)]
+pub struct Display {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct Visual {
+    _private: [u8; 0],
+}
+
+pub const True: Bool = 1;
+pub const False: Bool = 0;
+
+pub const AllocNone: c_int = 0;
+pub const InputOutput: c_uint = 1;
+
+pub const CWBorderPixel: c_ulong = 1 << 3;
+pub const CWEventMask: c_ulong = 1 << 11;
+pub const CWColormap: c_ulong = 1 << 13;
+
+pub const KeyPressMask: c_long = 1 << 0;
+pub const KeyReleaseMask: c_long = 1 << 1;
+pub const PointerMotionMask: c_long = 1 << 6;
+pub const ExposureMask: c_long = 1 << 15;
+pub const StructureNotifyMask: c_long = 1 << 17;
+pub const SubstructureNotifyMask: c_long = 1 << 19;
+pub const SubstructureRedirectMask: c_long = 1 << 20;
+pub const FocusChangeMask: c_long = 1 << 21;
+
+pub const KeyPress: c_int = 2;
+pub const KeyRelease: c_int = 3;
+pub const MotionNotify: c_int = 6;
+pub const ConfigureNotify: c_int = 22;
+pub const ClientMessage: c_int = 33;
+
+pub const PMinSize: c_long = 1 << 4;
+pub const PMaxSize: c_long = 1 << 5;
+
+/// `_NET_WM_STATE` client message actions
+pub const _NET_WM_STATE_REMOVE: c_long = 0;
+pub const _NET_WM_STATE_ADD: c_long = 1;
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct XVisualInfo {
+    pub visual: *mut Visual,
+    pub visualid: VisualID,
+    pub screen: c_int,
+    pub depth: c_int,
+    pub class: c_int,
+    pub red_mask: c_ulong,
+    pub green_mask: c_ulong,
+    pub blue_mask: c_ulong,
+    pub colormap_size: c_int,
+    pub bits_per_rgb: c_int,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct XSetWindowAttributes {
+    pub background_pixmap: Pixmap,
+    pub background_pixel: c_ulong,
+    pub border_pixmap: Pixmap,
+    pub border_pixel: c_ulong,
+    pub bit_gravity: c_int,
+    pub win_gravity: c_int,
+    pub backing_store: c_int,
+    pub backing_planes: c_ulong,
+    pub backing_pixel: c_ulong,
+    pub save_under: Bool,
+    pub event_mask: c_long,
+    pub do_not_propagate_mask: c_long,
+    pub override_redirect: Bool,
+    pub colormap: Colormap,
+    pub cursor: Cursor,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct XKeyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub keycode: c_uint,
+    pub same_screen: Bool,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct XMotionEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub is_hint: c_char,
+    pub same_screen: Bool,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct XConfigureEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub event: Window,
+    pub window: Window,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub border_width: c_int,
+    pub above: Window,
+    pub override_redirect: Bool,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct XClientMessageEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub message_type: Atom,
+    pub format: c_int,
+    pub data: [c_long; 5],
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct XAspect {
+    pub x: c_int,
+    pub y: c_int,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct XSizeHints {
+    pub flags: c_long,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub min_width: c_int,
+    pub min_height: c_int,
+    pub max_width: c_int,
+    pub max_height: c_int,
+    pub width_inc: c_int,
+    pub height_inc: c_int,
+    pub min_aspect: XAspect,
+    pub max_aspect: XAspect,
+    pub base_width: c_int,
+    pub base_height: c_int,
+    pub win_gravity: c_int,
+}
+
+/// Xlib's `XEvent` union, always 24 longs wide.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub union XEvent {
+    pub type_: c_int,
+    pub xkey: XKeyEvent,
+    pub xmotion: XMotionEvent,
+    pub xconfigure: XConfigureEvent,
+    pub xclient: XClientMessageEvent,
+    pub pad: [c_long; 24],
+}
+
+pub type XInitThreads = unsafe extern "C" fn() -> Status;
+pub type XOpenDisplay = unsafe extern "C" fn(_: *const c_char) -> *mut Display;
+pub type XCloseDisplay = unsafe extern "C" fn(_: *mut Display) -> c_int;
+pub type XDefaultScreen = unsafe extern "C" fn(_: *mut Display) -> c_int;
+pub type XRootWindow = unsafe extern "C" fn(_: *mut Display, _: c_int) -> Window;
+pub type XCreateColormap =
+    unsafe extern "C" fn(_: *mut Display, _: Window, _: *mut Visual, _: c_int) -> Colormap;
+pub type XFreeColormap = unsafe extern "C" fn(_: *mut Display, _: Colormap) -> c_int;
+pub type XCreateWindow = unsafe extern "C" fn(
+    _: *mut Display,
+    _: Window,
+    _: c_int,
+    _: c_int,
+    _: c_uint,
+    _: c_uint,
+    _: c_uint,
+    _: c_int,
+    _: c_uint,
+    _: *mut Visual,
+    _: c_ulong,
+    _: *mut XSetWindowAttributes,
+) -> Window;
+pub type XDestroyWindow = unsafe extern "C" fn(_: *mut Display, _: Window) -> c_int;
+pub type XMapWindow = unsafe extern "C" fn(_: *mut Display, _: Window) -> c_int;
+pub type XStoreName = unsafe extern "C" fn(_: *mut Display, _: Window, _: *const c_char) -> c_int;
+pub type XInternAtom = unsafe extern "C" fn(_: *mut Display, _: *const c_char, _: Bool) -> Atom;
+pub type XSetWMProtocols =
+    unsafe extern "C" fn(_: *mut Display, _: Window, _: *mut Atom, _: c_int) -> Status;
+pub type XPending = unsafe extern "C" fn(_: *mut Display) -> c_int;
+pub type XNextEvent = unsafe extern "C" fn(_: *mut Display, _: *mut XEvent) -> c_int;
+pub type XLookupKeysym = unsafe extern "C" fn(_: *mut XKeyEvent, _: c_int) -> KeySym;
+pub type XLookupString = unsafe extern "C" fn(
+    _: *mut XKeyEvent,
+    _: *mut c_char,
+    _: c_int,
+    _: *mut KeySym,
+    _: *mut std::ffi::c_void,
+) -> c_int;
+pub type XFlush = unsafe extern "C" fn(_: *mut Display) -> c_int;
+pub type XFree = unsafe extern "C" fn(_: *mut std::ffi::c_void) -> c_int;
+pub type XSetWMNormalHints = unsafe extern "C" fn(_: *mut Display, _: Window, _: *mut XSizeHints);
+pub type XSendEvent =
+    unsafe extern "C" fn(_: *mut Display, _: Window, _: Bool, _: c_long, _: *mut XEvent) -> Status;
+pub type XResourceManagerString = unsafe extern "C" fn(_: *mut Display) -> *mut c_char;
+pub type XErrorHandler =
+    Option<unsafe extern "C" fn(_: *mut Display, _: *mut std::ffi::c_void) -> c_int>;
+pub type XSetErrorHandler = unsafe extern "C" fn(_: XErrorHandler) -> XErrorHandler;
+pub type XSync = unsafe extern "C" fn(_: *mut Display, _: Bool) -> c_int;
+pub type XConnectionNumber = unsafe extern "C" fn(_: *mut Display) -> c_int;
+pub type XkbSetDetectableAutoRepeat =
+    unsafe extern "C" fn(_: *mut Display, _: Bool, _: *mut Bool) -> Bool;
+
+pub struct LibX11 {
+    pub _module: crate::native::module::Module,
+    pub XInitThreads: XInitThreads,
+    pub XOpenDisplay: XOpenDisplay,
+    pub XCloseDisplay: XCloseDisplay,
+    pub XDefaultScreen: XDefaultScreen,
+    pub XRootWindow: XRootWindow,
+    pub XCreateColormap: XCreateColormap,
+    pub XFreeColormap: XFreeColormap,
+    pub XCreateWindow: XCreateWindow,
+    pub XDestroyWindow: XDestroyWindow,
+    pub XMapWindow: XMapWindow,
+    pub XStoreName: XStoreName,
+    pub XInternAtom: XInternAtom,
+    pub XSetWMProtocols: XSetWMProtocols,
+    pub XPending: XPending,
+    pub XNextEvent: XNextEvent,
+    pub XLookupKeysym: XLookupKeysym,
+    pub XLookupString: XLookupString,
+    pub XFlush: XFlush,
+    pub XFree: XFree,
+    pub XSetWMNormalHints: XSetWMNormalHints,
+    pub XSendEvent: XSendEvent,
+    pub XResourceManagerString: XResourceManagerString,
+    pub XSetErrorHandler: XSetErrorHandler,
+    pub XSync: XSync,
+    pub XConnectionNumber: XConnectionNumber,
+    pub XkbSetDetectableAutoRepeat: XkbSetDetectableAutoRepeat,
+}
+
+impl LibX11 {
+    pub fn try_load() -> Option<LibX11> {
+        crate::native::module::Module::load("libX11.so")
+            .or_else(|_| crate::native::module::Module::load("libX11.so.6"))
+            .ok()
+            .and_then(|module| {
+                Some(LibX11 {
+                    XInitThreads: module.get_symbol("XInitThreads").ok()?,
+                    XOpenDisplay: module.get_symbol("XOpenDisplay").ok()?,
+                    XCloseDisplay: module.get_symbol("XCloseDisplay").ok()?,
+                    XDefaultScreen: module.get_symbol("XDefaultScreen").ok()?,
+                    XRootWindow: module.get_symbol("XRootWindow").ok()?,
+                    XCreateColormap: module.get_symbol("XCreateColormap").ok()?,
+                    XFreeColormap: module.get_symbol("XFreeColormap").ok()?,
+                    XCreateWindow: module.get_symbol("XCreateWindow").ok()?,
+                    XDestroyWindow: module.get_symbol("XDestroyWindow").ok()?,
+                    XMapWindow: module.get_symbol("XMapWindow").ok()?,
+                    XStoreName: module.get_symbol("XStoreName").ok()?,
+                    XInternAtom: module.get_symbol("XInternAtom").ok()?,
+                    XSetWMProtocols: module.get_symbol("XSetWMProtocols").ok()?,
+                    XPending: module.get_symbol("XPending").ok()?,
+                    XNextEvent: module.get_symbol("XNextEvent").ok()?,
+                    XLookupKeysym: module.get_symbol("XLookupKeysym").ok()?,
+                    XLookupString: module.get_symbol("XLookupString").ok()?,
+                    XFlush: module.get_symbol("XFlush").ok()?,
+                    XFree: module.get_symbol("XFree").ok()?,
+                    XSetWMNormalHints: module.get_symbol("XSetWMNormalHints").ok()?,
+                    XSendEvent: module.get_symbol("XSendEvent").ok()?,
+                    XResourceManagerString: module.get_symbol("XResourceManagerString").ok()?,
+                    XSetErrorHandler: module.get_symbol("XSetErrorHandler").ok()?,
+                    XSync: module.get_symbol("XSync").ok()?,
+                    XConnectionNumber: module.get_symbol("XConnectionNumber").ok()?,
+                    XkbSetDetectableAutoRepeat: module.get_symbol("XkbSetDetectableAutoRepeat").ok()?,
+                    _module: module,
+                })
+            })
+    }
+}
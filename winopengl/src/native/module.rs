@@ -43,4 +43,43 @@ mod windows {
     }
 }
 
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::Error;
+    use libc::{dlclose, dlopen, dlsym, RTLD_LAZY, RTLD_LOCAL};
+    use std::{
+        ffi::{c_void, CString},
+        ptr::NonNull,
+    };
+
+    pub struct Module(NonNull<c_void>);
+
+    impl Module {
+        pub fn load(path: &str) -> Result<Self, Error> {
+            let path = CString::new(path).unwrap();
+            let module = unsafe { dlopen(path.as_ptr(), RTLD_LAZY | RTLD_LOCAL) };
+
+            NonNull::new(module).map(Self).ok_or(Error::DlOpenError)
+        }
+        pub fn get_symbol<F: Sized>(&self, name: &str) -> Result<F, Error> {
+            let name = CString::new(name).unwrap();
+            let symbol = unsafe { dlsym(self.0.as_ptr(), name.as_ptr()) };
+
+            if symbol.is_null() {
+                return Err(Error::DlSymError);
+            }
+            Ok(unsafe { std::mem::transmute_copy(&symbol) })
+        }
+    }
+
+    impl Drop for Module {
+        fn drop(&mut self) {
+            unsafe { dlclose(self.0.as_ptr()) };
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
 pub use windows::Module;
+#[cfg(target_os = "linux")]
+pub use linux::Module;
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use crate::{conf::LoopMode, GraphicsContext};
+
+/// Frame timing shared by the backend main loops
+pub(crate) struct FramePacer {
+    mode: LoopMode,
+    last_update: Instant,
+}
+
+impl FramePacer {
+    pub fn new(mode: LoopMode) -> FramePacer {
+        FramePacer {
+            mode,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Seconds since the previous frame, the `dt` of `EventHandler::update`
+    pub fn begin_frame(&mut self, context: &mut GraphicsContext) -> f32 {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        context.begin_frame(dt);
+        if context.next_update.is_some_and(|update| update <= now) {
+            context.next_update = None;
+        }
+        dt
+    }
+
+    /// Right after the buffer swap, before any sleep
+    pub fn end_frame(&self, context: &mut GraphicsContext) {
+        context.end_frame(self.last_update.elapsed().as_secs_f32());
+    }
+
+    /// With `LoopMode::TargetFps`, sleep for what is left of the frame
+    pub fn sleep_until_next_frame(&self) {
+        if let LoopMode::TargetFps(fps) = self.mode {
+            let frame = Duration::from_secs_f64(1. / fps.max(1) as f64);
+            if let Some(left) = frame.checked_sub(self.last_update.elapsed()) {
+                std::thread::sleep(left);
+            }
+        }
+    }
+
+    /// How long the backend may block waiting for events before the next frame.
+    /// Zero unless `LoopMode::WaitEvents`, `None` to wait for an event when no update is scheduled.
+    pub fn event_timeout(&self, context: &GraphicsContext) -> Option<Duration> {
+        match self.mode {
+            LoopMode::WaitEvents => context
+                .next_update
+                .map(|update| update.saturating_duration_since(Instant::now())),
+            _ => Some(Duration::ZERO),
+        }
+    }
+}
+
+/// Milliseconds for the platform wait functions, rounded up so the wakeup is never early
+pub(crate) fn timeout_millis(timeout: Duration) -> u32 {
+    timeout.as_micros().div_ceil(1000).min(u32::MAX as u128 - 1) as u32
+}
@@ -2,18 +2,21 @@ use std::ffi::CString;
 
 use crate::{
     conf::Conf,
-    native::NativeDisplayData,
-    GraphicsContext, EventHandler,
+    native::{pacing::{self, FramePacer}, NativeDisplayData},
+    Error, GraphicsContext, EventHandler,
 };
 
 use winapi::{
     shared::{
-        minwindef::{DWORD, HIWORD, LPARAM, LRESULT, UINT, WPARAM},
+        minwindef::{DWORD, HIWORD, LOWORD, LPARAM, LRESULT, UINT, WPARAM},
         ntdef::{NULL},
         windef::{HDC, HWND, RECT},
+        windowsx::{GET_X_LPARAM, GET_Y_LPARAM},
     },
     um::{
         libloaderapi::{GetModuleHandleW, GetProcAddress},
+        winbase::INFINITE,
+        shellscalingapi::{GetDpiForMonitor, SetProcessDpiAwareness, MDT_EFFECTIVE_DPI, PROCESS_PER_MONITOR_DPI_AWARE},
         wingdi::*,
         winuser::*,
     },
@@ -32,6 +35,11 @@ pub(crate) struct Display {
     msg_dc: HDC,
     wnd: HWND,
     dc: HDC,
+    high_dpi: bool,
+    /// Requested by `set_fullscreen`, applied by the main loop outside of any window message
+    fullscreen_request: Option<bool>,
+    /// Style and position to go back to when leaving fullscreen
+    windowed: Option<(DWORD, RECT)>,
 }
 
 impl crate::native::NativeDisplay for Display {
@@ -50,11 +58,75 @@ impl crate::native::NativeDisplay for Display {
         }
     }
 
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        // resizing sends WM_SIZE right away, the event handler calling this must return first
+        self.fullscreen_request = Some(fullscreen);
+    }
+
     fn as_any(&mut self) -> &mut dyn std::any::Any {
         self
     }
 }
 
+impl Display {
+    unsafe fn apply_fullscreen(&mut self) {
+        let fullscreen = match self.fullscreen_request.take() {
+            Some(fullscreen) if fullscreen != self.windowed.is_some() => fullscreen,
+            _ => return,
+        };
+
+        if fullscreen {
+            let style = GetWindowLongW(self.wnd, GWL_STYLE) as DWORD;
+            let mut rect: RECT = std::mem::zeroed();
+            GetWindowRect(self.wnd, &mut rect);
+            self.windowed = Some((style, rect));
+
+            let monitor = MonitorFromWindow(self.wnd, MONITOR_DEFAULTTONEAREST);
+            let mut info: MONITORINFO = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<MONITORINFO>() as _;
+            GetMonitorInfoW(monitor, &mut info);
+            let rect = info.rcMonitor;
+
+            SetWindowLongW(self.wnd, GWL_STYLE, (WS_POPUP | WS_VISIBLE) as _);
+            SetWindowPos(
+                self.wnd,
+                HWND_TOP,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_FRAMECHANGED | SWP_SHOWWINDOW,
+            );
+        } else if let Some((style, rect)) = self.windowed.take() {
+            SetWindowLongW(self.wnd, GWL_STYLE, style as _);
+            SetWindowPos(
+                self.wnd,
+                HWND_TOP,
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_FRAMECHANGED | SWP_SHOWWINDOW,
+            );
+        }
+    }
+
+    unsafe fn client_size(&self) -> (i32, i32) {
+        let mut rect: RECT = std::mem::zeroed();
+        GetClientRect(self.wnd, &mut rect);
+        (rect.right - rect.left, rect.bottom - rect.top)
+    }
+}
+
+unsafe fn monitor_dpi_scale(wnd: HWND) -> f32 {
+    let monitor = MonitorFromWindow(wnd, MONITOR_DEFAULTTONEAREST);
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) != 0 {
+        return 1.;
+    }
+    dpi_x as f32 / 96.
+}
+
 struct WindowPayload {
     event_handler: Box<dyn EventHandler>,
     context: GraphicsContext,
@@ -83,11 +155,50 @@ unsafe extern "system" fn win32_wndproc(
             return 0;
         }
 
+        WM_SIZE => {
+            let (width, height) = (LOWORD(lparam as _) as i32, HIWORD(lparam as _) as i32);
+            if wparam != SIZE_MINIMIZED && width > 0 && height > 0 && (width, height) != context.screen_size() {
+                context.resize(width, height);
+                event_handler.resize_event(context.with_display(display), width, height);
+            }
+        }
+        WM_DPICHANGED => {
+            if display.high_dpi {
+                context.dpi_scale = HIWORD(wparam as _) as f32 / 96.;
+                // moving to the suggested rect sends the WM_SIZE
+                let rect = &*(lparam as *const RECT);
+                SetWindowPos(
+                    hwnd,
+                    NULL as _,
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOZORDER | SWP_NOACTIVATE,
+                );
+                return 0;
+            }
+        }
         WM_KEYDOWN | WM_SYSKEYDOWN => {
             let keycode = HIWORD(lparam as _) as u32 & 0x1FF;
             let keycode = keycodes::translate_keycode(keycode);
             event_handler.key_down_event(context.with_display(display), keycode);
         }
+        WM_KEYUP | WM_SYSKEYUP => {
+            let keycode = HIWORD(lparam as _) as u32 & 0x1FF;
+            let keycode = keycodes::translate_keycode(keycode);
+            event_handler.key_up_event(context.with_display(display), keycode);
+        }
+        WM_CHAR => {
+            // surrogate pairs are dropped, nothing here needs them
+            if let Some(character) = char::from_u32(wparam as u32).filter(|c| !c.is_control()) {
+                event_handler.char_event(context.with_display(display), character);
+            }
+        }
+        WM_MOUSEMOVE => {
+            let (x, y) = (GET_X_LPARAM(lparam) as f32, GET_Y_LPARAM(lparam) as f32);
+            event_handler.mouse_motion_event(context.with_display(display), x, y);
+        }
         _ => {}
     }
 
@@ -98,7 +209,8 @@ unsafe fn create_window(
     window_title: &str,
     width: i32,
     height: i32,
-) -> (HWND, HDC) {
+    resizable: bool,
+) -> Result<(HWND, HDC), Error> {
     let mut wndclassw: WNDCLASSW = std::mem::zeroed();
 
     wndclassw.style = CS_HREDRAW | CS_VREDRAW | CS_OWNDC;
@@ -119,7 +231,10 @@ unsafe fn create_window(
         bottom: 0,
     };
     
-    let win_style: DWORD = WS_CLIPSIBLINGS | WS_CLIPCHILDREN | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX;
+    let mut win_style: DWORD = WS_CLIPSIBLINGS | WS_CLIPCHILDREN | WS_CAPTION | WS_SYSMENU | WS_MINIMIZEBOX;
+    if resizable {
+        win_style |= WS_THICKFRAME | WS_MAXIMIZEBOX;
+    }
 
     rect.right = width;
     rect.bottom = height;
@@ -144,15 +259,19 @@ unsafe fn create_window(
         GetModuleHandleW(NULL as _), // hInstance
         NULL as _,                   // lparam
     );
-    assert!(!hwnd.is_null());
+    if hwnd.is_null() {
+        return Err(Error::Window("Win32: CreateWindowExW failed".to_string()));
+    }
     ShowWindow(hwnd, SW_SHOW);
     let dc = GetDC(hwnd);
-    assert!(!dc.is_null());
+    if dc.is_null() {
+        return Err(Error::Window("Win32: failed to obtain window DC".to_string()));
+    }
 
-    (hwnd, dc)
+    Ok((hwnd, dc))
 }
 
-unsafe fn create_msg_window() -> (HWND, HDC) {
+unsafe fn create_msg_window() -> Result<(HWND, HDC), Error> {
     let class_name = "MINIQUADAPP\0".encode_utf16().collect::<Vec<u16>>();
     let window_name = "miniquad message window\0"
         .encode_utf16()
@@ -171,10 +290,9 @@ unsafe fn create_msg_window() -> (HWND, HDC) {
         GetModuleHandleW(NULL as _),
         NULL,
     );
-    assert!(
-        !msg_hwnd.is_null(),
-        "Win32: failed to create helper window!"
-    );
+    if msg_hwnd.is_null() {
+        return Err(Error::Window("Win32: failed to create helper window".to_string()));
+    }
     ShowWindow(msg_hwnd, SW_HIDE);
     let mut msg = std::mem::zeroed();
     while PeekMessageW(&mut msg as _, msg_hwnd, 0, 0, PM_REMOVE) != 0 {
@@ -182,43 +300,68 @@ unsafe fn create_msg_window() -> (HWND, HDC) {
         DispatchMessageW(&msg);
     }
     let msg_dc = GetDC(msg_hwnd);
-    assert!(
-        !msg_dc.is_null(),
-        "Win32: failed to obtain helper window DC!"
-    );
+    if msg_dc.is_null() {
+        return Err(Error::Window("Win32: failed to obtain helper window DC".to_string()));
+    }
 
-    (msg_hwnd, msg_dc)
+    Ok((msg_hwnd, msg_dc))
 }
 
 impl Display {
     unsafe fn get_proc_address(&mut self, proc: &str) -> Option<unsafe extern "C" fn() -> ()> {
         let proc = std::ffi::CString::new(proc).unwrap();
         let mut proc_ptr = (self.libopengl32.wglGetProcAddress)(proc.as_ptr());
-        if proc_ptr.is_null() {
+        // wglGetProcAddress only knows the functions past GL 1.1, and some drivers return 1, 2, 3 or -1 for missing ones
+        if matches!(proc_ptr as isize, -1 | 0..=3) {
             proc_ptr = GetProcAddress(self.libopengl32.module.0, proc.as_ptr());
         }
-        assert!(
-            !proc_ptr.is_null(),
-            "Load GL func {:?} failed.",
-            stringify!($fn)
-        );
-        Some(std::mem::transmute(proc_ptr))
+        if proc_ptr.is_null() {
+            None
+        } else {
+            Some(std::mem::transmute(proc_ptr))
+        }
     }
 }
 
-pub fn run<F>(conf: &Conf, f: F)
+pub fn run<F>(conf: &Conf, f: F) -> Result<(), Error>
 where
-    F: 'static + FnOnce(&mut GraphicsContext) -> Box<dyn EventHandler>,
+    F: 'static + FnOnce(&mut GraphicsContext) -> Result<Box<dyn EventHandler>, Error>,
 {
     unsafe {
+        if conf.high_dpi {
+            // otherwise windows renders at 96 dpi and upscales the result
+            SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE);
+        }
         let (wnd, dc) = create_window(
             &conf.window_title,
             conf.window_width as _,
             conf.window_height as _,
-        );
-        let libopengl32 = LibOpengl32::try_load().expect("Failed to load opengl32.dll.");
+            conf.window_resizable,
+        )?;
+        let dpi_scale = if conf.high_dpi { monitor_dpi_scale(wnd) } else { 1. };
+        if dpi_scale != 1. {
+            let style = GetWindowLongW(wnd, GWL_STYLE) as DWORD;
+            let mut rect = RECT {
+                left: 0,
+                top: 0,
+                right: (conf.window_width as f32 * dpi_scale) as _,
+                bottom: (conf.window_height as f32 * dpi_scale) as _,
+            };
+            AdjustWindowRectEx(&mut rect, style, false as _, WS_EX_APPWINDOW | WS_EX_WINDOWEDGE);
+            SetWindowPos(
+                wnd,
+                NULL as _,
+                0,
+                0,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOZORDER | SWP_NOACTIVATE | SWP_NOMOVE,
+            );
+        }
+        let libopengl32 =
+            LibOpengl32::try_load().ok_or_else(|| Error::Context("failed to load opengl32.dll".to_string()))?;
 
-        let (msg_wnd, msg_dc) = create_msg_window();
+        let (msg_wnd, msg_dc) = create_msg_window()?;
         let mut display = Display {
             display_data: Default::default(),
             libopengl32,
@@ -226,21 +369,23 @@ where
             msg_dc,
             wnd,
             dc,
+            high_dpi: conf.high_dpi,
+            fullscreen_request: if conf.fullscreen { Some(true) } else { None },
+            windowed: None,
         };
+        display.apply_fullscreen();
 
-        let mut wgl = wgl::Wgl::new(&mut display);
-        let gl_ctx = wgl.create_context(
-            &mut display,
-            1,
-            1,
-        );
-
-        super::gl::load_gl_funcs(|proc| display.get_proc_address(proc));
+        let mut wgl = wgl::Wgl::new(&mut display)?;
+        let (gl_ctx, fell_back) = wgl.create_context(&mut display, conf)?;
+        let swap_interval = wgl.set_swap_interval(conf.swap_interval);
 
-        let mut context = GraphicsContext::new(conf.window_width, conf.window_height);
-        context.features.instancing = !crate::gl::is_gl2();
+        let size = display.client_size();
+        let mut context = super::new_graphics_context(conf, size, swap_interval, fell_back, |proc| {
+            display.get_proc_address(proc)
+        })?;
+        context.dpi_scale = dpi_scale;
 
-        let event_handler = f(context.with_display(&mut display));
+        let event_handler = f(context.with_display(&mut display))?;
 
         let mut p = WindowPayload {
             display,
@@ -251,6 +396,7 @@ where
         // so if there going to be some weird bugs someday in the future - check this out!
         SetWindowLongPtrA(wnd, GWLP_USERDATA, &mut p as *mut _ as isize);
 
+        let mut pacer = FramePacer::new(conf.loop_mode);
         let mut done = false;
         while !(done || p.display.display_data.quit_ordered) {
             let mut msg: MSG = std::mem::zeroed();
@@ -263,11 +409,36 @@ where
                     DispatchMessageW(&mut msg as *mut _ as _);
                 }
             }
-            p.event_handler.update(p.context.with_display(&mut p.display));
+            let dt = pacer.begin_frame(&mut p.context);
+            p.event_handler.update(p.context.with_display(&mut p.display), dt);
             p.event_handler.draw(p.context.with_display(&mut p.display));
             SwapBuffers(p.display.dc);
+            pacer.end_frame(&mut p.context);
+            p.display.apply_fullscreen();
+            pacer.sleep_until_next_frame();
+            let timeout = pacer.event_timeout(&p.context);
+            if timeout != Some(std::time::Duration::ZERO) {
+                // returns on the first message queued, they are handled at the top of the loop
+                MsgWaitForMultipleObjects(
+                    0,
+                    std::ptr::null(),
+                    false as _,
+                    timeout.map_or(INFINITE, pacing::timeout_millis),
+                    QS_ALLINPUT,
+                );
+            }
         }
-        (p.display.libopengl32.wglDeleteContext)(gl_ctx);
+        SetWindowLongPtrA(wnd, GWLP_USERDATA, 0);
+        let WindowPayload {
+            display,
+            mut context,
+            event_handler,
+        } = p;
+        drop(event_handler);
+        context.report_leaks();
+
+        (display.libopengl32.wglDeleteContext)(gl_ctx);
         DestroyWindow(wnd);
     }
+    Ok(())
 }
@@ -9,6 +9,15 @@ pub fn translate_keycode(keycode: u32) -> KeyCode {
         0x14B => KeyCode::Left,
         0x14D => KeyCode::Right,
         0x148 => KeyCode::Up,
+        0x03D => KeyCode::F3,
+        0x057 => KeyCode::F11,
+        0x058 => KeyCode::F12,
+        0x149 => KeyCode::PageUp,
+        0x151 => KeyCode::PageDown,
+        0x01C => KeyCode::Enter,
+        0x00E => KeyCode::Backspace,
+        0x00F => KeyCode::Tab,
+        0x029 => KeyCode::GraveAccent,
         _ => KeyCode::Unknown,
     }
 }
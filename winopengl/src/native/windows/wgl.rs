@@ -8,6 +8,11 @@ use winapi::{
 };
 
 use super::{Display, LibOpengl32};
+use crate::{
+    conf::{Conf, GlProfile},
+    gl::{WGL_CONTEXT_DEBUG_BIT_ARB, WGL_CONTEXT_FLAGS_ARB},
+    Error,
+};
 
 pub const WGL_NUMBER_PIXEL_FORMATS_ARB: u32 = 0x2000;
 pub const WGL_SUPPORT_OPENGL_ARB: u32 = 0x2010;
@@ -24,10 +29,12 @@ pub const WGL_DEPTH_BITS_ARB: u32 = 0x2022;
 pub const WGL_STENCIL_BITS_ARB: u32 = 0x2023;
 pub const WGL_DOUBLE_BUFFER_ARB: u32 = 0x2011;
 pub const WGL_SAMPLES_ARB: u32 = 0x2042;
+pub const WGL_CONTEXT_PROFILE_MASK_ARB: u32 = 0x9126;
 pub const WGL_CONTEXT_CORE_PROFILE_BIT_ARB: u32 = 0x00000001;
+pub const WGL_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB: u32 = 0x00000002;
+pub const WGL_CONTEXT_ES2_PROFILE_BIT_EXT: u32 = 0x00000004;
 pub const WGL_CONTEXT_MAJOR_VERSION_ARB: u32 = 0x2091;
 pub const WGL_CONTEXT_MINOR_VERSION_ARB: u32 = 0x2092;
-pub const WGL_CONTEXT_FLAGS_ARB: u32 = 0x2094;
 pub const ERROR_INVALID_VERSION_ARB: u32 = 0x2095;
 pub const ERROR_INVALID_PROFILE_ARB: u32 = 0x2096;
 pub const ERROR_INCOMPATIBLE_DEVICE_CONTEXTS_ARB: u32 = 0x2054;
@@ -169,6 +176,7 @@ pub struct Wgl {
     arb_multisample: bool,
     arb_create_context: bool,
     arb_create_context_profile: bool,
+    ext_create_context_es2_profile: bool,
     ext_swap_control: bool,
 }
 
@@ -183,7 +191,7 @@ unsafe fn get_wgl_proc_address<T>(libopengl32: &mut LibOpengl32, proc: &str) ->
 }
 
 impl Wgl {
-    pub(crate) unsafe fn new(display: &mut Display) -> Wgl {
+    pub(crate) unsafe fn new(display: &mut Display) -> Result<Wgl, Error> {
         let mut pfd: PIXELFORMATDESCRIPTOR = std::mem::zeroed();
         pfd.nSize = std::mem::size_of_val(&pfd) as _;
         pfd.nVersion = 1;
@@ -196,14 +204,14 @@ impl Wgl {
             &pfd,
         ) == 0
         {
-            panic!("WGL: failed to set pixel format for dummy context");
+            return Err(Error::PixelFormat("WGL: failed to set pixel format for dummy context".to_string()));
         }
         let rc = (display.libopengl32.wglCreateContext)(display.msg_dc);
         if rc.is_null() {
-            panic!("WGL: Failed to create dummy context");
+            return Err(Error::Context("WGL: failed to create dummy context".to_string()));
         }
         if (display.libopengl32.wglMakeCurrent)(display.msg_dc, rc) == false {
-            panic!("WGL: Failed to make context current");
+            return Err(Error::Context("WGL: failed to make dummy context current".to_string()));
         }
 
         let GetExtensionsStringEXT: Option<GetExtensionsStringEXT> =
@@ -245,13 +253,16 @@ impl Wgl {
         let arb_multisample = wgl_ext_supported("WGL_ARB_multisample");
         let arb_create_context = wgl_ext_supported("WGL_ARB_create_context");
         let arb_create_context_profile = wgl_ext_supported("WGL_ARB_create_context_profile");
+        let ext_create_context_es2_profile = wgl_ext_supported("WGL_EXT_create_context_es2_profile");
         let ext_swap_control = wgl_ext_supported("WGL_EXT_swap_control");
         let arb_pixel_format = wgl_ext_supported("WGL_ARB_pixel_format");
-        assert!(arb_pixel_format, "WGL_ARB_pixel_format is required");
 
         (display.libopengl32.wglDeleteContext)(rc);
+        if !arb_pixel_format {
+            return Err(Error::PixelFormat("WGL: WGL_ARB_pixel_format is required".to_string()));
+        }
 
-        Wgl {
+        Ok(Wgl {
             GetPixelFormatAttribivARB,
             CreateContextAttribsARB,
             SwapIntervalEXT,
@@ -259,11 +270,12 @@ impl Wgl {
             arb_multisample,
             arb_create_context,
             arb_create_context_profile,
+            ext_create_context_es2_profile,
             ext_swap_control,
-        }
+        })
     }
 
-    unsafe fn wgl_attrib(&self, display: &mut Display, pixel_format: i32, attrib: i32) -> i32 {
+    unsafe fn wgl_attrib(&self, display: &mut Display, pixel_format: i32, attrib: i32) -> Result<i32, Error> {
         let mut value = 0;
         if !(self.GetPixelFormatAttribivARB.unwrap())(
             display.dc,
@@ -273,13 +285,13 @@ impl Wgl {
             &attrib,
             &mut value as *mut _,
         ) {
-            panic!("WGL: Failed to retrieve pixel format attribute");
+            return Err(Error::PixelFormat("WGL: failed to retrieve pixel format attribute".to_string()));
         }
-        value
+        Ok(value)
     }
 
-    unsafe fn wgl_find_pixel_format(&self, display: &mut Display, sample_count: i32) -> u32 {
-        let native_count = self.wgl_attrib(display, 1, WGL_NUMBER_PIXEL_FORMATS_ARB as _);
+    unsafe fn wgl_find_pixel_format(&self, display: &mut Display, sample_count: i32) -> Result<u32, Error> {
+        let native_count = self.wgl_attrib(display, 1, WGL_NUMBER_PIXEL_FORMATS_ARB as _)?;
         let mut usable_configs = vec![GlFbconfig::default(); native_count as usize];
 
         let mut usable_count = 0;
@@ -287,35 +299,37 @@ impl Wgl {
             let n = i + 1;
             let u = &mut usable_configs[usable_count];
             *u = Default::default();
-            if self.wgl_attrib(display, n, WGL_SUPPORT_OPENGL_ARB as _) == 0
-                || self.wgl_attrib(display, n, WGL_DRAW_TO_WINDOW_ARB as _) == 0
+            if self.wgl_attrib(display, n, WGL_SUPPORT_OPENGL_ARB as _)? == 0
+                || self.wgl_attrib(display, n, WGL_DRAW_TO_WINDOW_ARB as _)? == 0
             {
                 continue;
             }
-            if self.wgl_attrib(display, n, WGL_PIXEL_TYPE_ARB as _) != WGL_TYPE_RGBA_ARB as _ {
+            if self.wgl_attrib(display, n, WGL_PIXEL_TYPE_ARB as _)? != WGL_TYPE_RGBA_ARB as _ {
                 continue;
             }
-            if self.wgl_attrib(display, n, WGL_ACCELERATION_ARB as _)
+            if self.wgl_attrib(display, n, WGL_ACCELERATION_ARB as _)?
                 == WGL_NO_ACCELERATION_ARB as _
             {
                 continue;
             }
-            u.red_bits = self.wgl_attrib(display, n, WGL_RED_BITS_ARB as _);
-            u.green_bits = self.wgl_attrib(display, n, WGL_GREEN_BITS_ARB as _);
-            u.blue_bits = self.wgl_attrib(display, n, WGL_BLUE_BITS_ARB as _);
-            u.alpha_bits = self.wgl_attrib(display, n, WGL_ALPHA_BITS_ARB as _);
-            u.depth_bits = self.wgl_attrib(display, n, WGL_DEPTH_BITS_ARB as _);
-            u.stencil_bits = self.wgl_attrib(display, n, WGL_STENCIL_BITS_ARB as _);
-            if self.wgl_attrib(display, n, WGL_DOUBLE_BUFFER_ARB as _) != 0 {
+            u.red_bits = self.wgl_attrib(display, n, WGL_RED_BITS_ARB as _)?;
+            u.green_bits = self.wgl_attrib(display, n, WGL_GREEN_BITS_ARB as _)?;
+            u.blue_bits = self.wgl_attrib(display, n, WGL_BLUE_BITS_ARB as _)?;
+            u.alpha_bits = self.wgl_attrib(display, n, WGL_ALPHA_BITS_ARB as _)?;
+            u.depth_bits = self.wgl_attrib(display, n, WGL_DEPTH_BITS_ARB as _)?;
+            u.stencil_bits = self.wgl_attrib(display, n, WGL_STENCIL_BITS_ARB as _)?;
+            if self.wgl_attrib(display, n, WGL_DOUBLE_BUFFER_ARB as _)? != 0 {
                 u.doublebuffer = true;
             }
             if self.arb_multisample {
-                u.samples = self.wgl_attrib(display, n, WGL_SAMPLES_ARB as _);
+                u.samples = self.wgl_attrib(display, n, WGL_SAMPLES_ARB as _)?;
             }
             u.handle = n as _;
             usable_count += 1;
         }
-        assert!(usable_count > 0);
+        if usable_count == 0 {
+            return Err(Error::PixelFormat("WGL: no accelerated RGBA pixel format".to_string()));
+        }
 
         let mut desired = GlFbconfig::default();
         desired.red_bits = 8;
@@ -325,24 +339,21 @@ impl Wgl {
         desired.depth_bits = 24;
         desired.stencil_bits = 8;
         desired.doublebuffer = true;
-        desired.samples = sample_count;
+        desired.samples = if sample_count > 1 && self.arb_multisample { sample_count } else { 0 };
         let closest = gl_choose_fbconfig(&mut desired, &usable_configs[..]);
         let mut pixel_format = 0;
         if let Some(closest) = closest {
             pixel_format = usable_configs[closest].handle;
         }
-        pixel_format
+        Ok(pixel_format)
     }
 
-    pub(crate) unsafe fn create_context(
-        &mut self,
-        display: &mut Display,
-        sample_count: i32,
-        swap_interval: i32,
-    ) -> HGLRC {
-        let pixel_format = self.wgl_find_pixel_format(display, sample_count);
+    /// Context with the `Conf` version and profile, or the 2.1 / ES 2.0 fallback.
+    /// Also returns whether the fallback was used.
+    pub(crate) unsafe fn create_context(&mut self, display: &mut Display, conf: &Conf) -> Result<(HGLRC, bool), Error> {
+        let pixel_format = self.wgl_find_pixel_format(display, conf.sample_count)?;
         if 0 == pixel_format {
-            panic!("WGL: Didn't find matching pixel format.");
+            return Err(Error::PixelFormat("WGL: no pixel format matches".to_string()));
         }
         let mut pfd: PIXELFORMATDESCRIPTOR = std::mem::zeroed();
         if DescribePixelFormat(
@@ -352,50 +363,82 @@ impl Wgl {
             &mut pfd as *mut _ as _,
         ) == 0
         {
-            panic!("WGL: Failed to retrieve PFD for selected pixel format!");
+            return Err(Error::PixelFormat("WGL: failed to retrieve PFD for selected pixel format".to_string()));
         }
         if SetPixelFormat(display.dc, pixel_format as _, &pfd) == 0 {
-            panic!("WGL: Failed to set selected pixel format!");
+            return Err(Error::PixelFormat("WGL: failed to set selected pixel format".to_string()));
         }
         if !self.arb_create_context {
-            panic!("WGL: ARB_create_context required!\n");
+            return Err(Error::Context("WGL: ARB_create_context required".to_string()));
         }
         if !self.arb_create_context_profile {
-            panic!("WGL: ARB_create_context_profile required!");
+            return Err(Error::Context("WGL: ARB_create_context_profile required".to_string()));
+        }
+        // what was asked, then the versions every driver has
+        let mut attempts = vec![(conf.gl_version, conf.gl_profile)];
+        if conf.gl_profile == GlProfile::Es {
+            attempts.push(((2, 0), GlProfile::Es));
+        }
+        attempts.push(((2, 1), GlProfile::Compatibility));
+        // KHR_debug only reports everything on a debug context
+        let flags = if conf.gl_debug { WGL_CONTEXT_DEBUG_BIT_ARB } else { 0 };
+        let mut gl_ctx = std::ptr::null_mut();
+        let mut fell_back = false;
+        for (i, &((major, minor), profile)) in attempts.iter().enumerate() {
+            let profile_mask = match profile {
+                GlProfile::Compatibility => WGL_CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+                GlProfile::Core => WGL_CONTEXT_CORE_PROFILE_BIT_ARB,
+                GlProfile::Es if self.ext_create_context_es2_profile => WGL_CONTEXT_ES2_PROFILE_BIT_EXT,
+                GlProfile::Es => continue,
+            };
+            let attrs = [
+                WGL_CONTEXT_MAJOR_VERSION_ARB,
+                major as u32,
+                WGL_CONTEXT_MINOR_VERSION_ARB,
+                minor as u32,
+                WGL_CONTEXT_PROFILE_MASK_ARB,
+                profile_mask,
+                WGL_CONTEXT_FLAGS_ARB,
+                flags,
+                0,
+                0,
+            ];
+            gl_ctx = self.CreateContextAttribsARB.unwrap()(
+                display.dc,
+                std::ptr::null_mut(),
+                attrs.as_ptr() as *const _,
+            );
+            if !gl_ctx.is_null() {
+                fell_back = i > 0;
+                break;
+            }
         }
-        let attrs = [
-            WGL_CONTEXT_MAJOR_VERSION_ARB,
-            2,
-            WGL_CONTEXT_MINOR_VERSION_ARB,
-            1,
-            WGL_CONTEXT_FLAGS_ARB,
-            WGL_CONTEXT_CORE_PROFILE_BIT_ARB,
-            0,
-            0,
-        ];
-        let gl_ctx = self.CreateContextAttribsARB.unwrap()(
-            display.dc,
-            std::ptr::null_mut(),
-            attrs.as_ptr() as *const _,
-        );
         if gl_ctx.is_null() {
             let err = GetLastError();
-            if err == (0xc0070000 | ERROR_INVALID_VERSION_ARB) {
-                panic!("WGL: Driver does not support OpenGL version 3.3");
+            let message = if err == (0xc0070000 | ERROR_INVALID_VERSION_ARB) {
+                "WGL: Driver does not support the requested OpenGL version"
             } else if err == (0xc0070000 | ERROR_INVALID_PROFILE_ARB) {
-                panic!("WGL: Driver does not support the requested OpenGL profile");
+                "WGL: Driver does not support the requested OpenGL profile"
             } else if err == (0xc0070000 | ERROR_INCOMPATIBLE_DEVICE_CONTEXTS_ARB) {
-                panic!("WGL: The share context is not compatible with the requested context");
+                "WGL: The share context is not compatible with the requested context"
             } else {
-                panic!("WGL: Failed to create OpenGL context");
-            }
+                "WGL: Failed to create OpenGL context"
+            };
+            return Err(Error::Context(message.to_string()));
         }
         (display.libopengl32.wglMakeCurrent)(display.dc, gl_ctx);
-        if self.ext_swap_control {
-            /* FIXME: DwmIsCompositionEnabled() (see GLFW) */
-            (self.SwapIntervalEXT.unwrap())(swap_interval);
-        }
 
-        gl_ctx
+        Ok((gl_ctx, fell_back))
+    }
+
+    /// Returns the interval set, `None` without WGL_EXT_swap_control
+    pub(crate) unsafe fn set_swap_interval(&mut self, swap_interval: i32) -> Option<i32> {
+        /* FIXME: DwmIsCompositionEnabled() (see GLFW) */
+        match self.SwapIntervalEXT {
+            Some(swap_interval_ext) if self.ext_swap_control && swap_interval_ext(swap_interval) => {
+                Some(swap_interval)
+            }
+            _ => None,
+        }
     }
 }